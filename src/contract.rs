@@ -3,7 +3,7 @@ use crate::{
     error::AppError,
     handlers,
     msg::{AppInstantiateMsg, DCAExecuteMsg, DCAQueryMsg},
-    replies::{self, INSTANTIATE_REPLY_ID},
+    replies::{self, HOOK_REPLY_ID, INSTANTIATE_REPLY_ID, SWAP_REPLY_ID},
 };
 use abstract_app::AppContract;
 use abstract_core::objects::dependency::StaticDependency;
@@ -27,7 +27,11 @@ const DCA_APP: DCAApp = DCAApp::new(DCA_APP_ID, DCA_APP_VERSION, None)
     .with_execute(handlers::execute_handler)
     .with_query(handlers::query_handler)
     .with_migrate(handlers::migrate_handler)
-    .with_replies(&[(INSTANTIATE_REPLY_ID, replies::instantiate_reply)])
+    .with_replies(&[
+        (INSTANTIATE_REPLY_ID, replies::instantiate_reply),
+        (SWAP_REPLY_ID, replies::swap_reply),
+        (HOOK_REPLY_ID, replies::hook_reply),
+    ])
     .with_dependencies(&[
         StaticDependency::new(CRONCAT_ID, &[CRONCAT_MODULE_VERSION]),
         StaticDependency::new(
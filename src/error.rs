@@ -3,7 +3,7 @@ use abstract_core::AbstractError;
 use abstract_sdk::AbstractSdkError;
 use cosmwasm_std::StdError;
 use cw_asset::AssetError;
-use cw_controllers::AdminError;
+use cw_controllers::{AdminError, HookError};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -26,6 +26,75 @@ pub enum AppError {
     #[error("{0}")]
     DappError(#[from] AbstractAppError),
 
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
     #[error("Convert can be called only by the croncat manager")]
     NotManagerConvert {},
+
+    #[error("Only the app itself can call this")]
+    NotSelf {},
+
+    #[error("All DCAs are halted")]
+    Halted {},
+
+    #[error("DCA {dca_id} is not active")]
+    DCANotActive { dca_id: String },
+
+    #[error("DCA {dca_id} is not paused or failed")]
+    DCANotPaused { dca_id: String },
+
+    #[error("Start and end of the DCA must be of the same kind, with start before end")]
+    InvalidBoundaries {},
+
+    #[error("Max spread must be between 0 and 1")]
+    InvalidMaxSpread {},
+
+    #[error("Total budget and max executions must be positive")]
+    InvalidLimits {},
+
+    #[error("Min price must not be above max price")]
+    InvalidPriceRange {},
+
+    #[error("Balance percentage must be between 0 and 1, with min amount not above max amount")]
+    InvalidBalancePercentage {},
+
+    #[error("Exact output amount must be positive, with a buffer between 0 and 1")]
+    InvalidExactOutput {},
+
+    #[error("Value increment must be positive")]
+    InvalidValueIncrement {},
+
+    #[error("Dip tiers need drops between 0 and 1, positive multipliers up to 10 and a fixed or balance percentage strategy")]
+    InvalidDipTiers {},
+
+    #[error("Recipient can't be combined with a target holding or value averaging")]
+    InvalidRecipient {},
+
+    #[error("Delegation needs a native target asset and can't be combined with a recipient, a target holding or value averaging")]
+    InvalidPostSwapAction {},
+
+    #[error("Route must not contain the source or target asset")]
+    InvalidRoute {},
+
+    #[error("Gas limit of the task must be positive")]
+    InvalidGasLimit {},
+
+    #[error("Failure policy must allow at least one failure before pausing")]
+    InvalidFailurePolicy {},
+
+    #[error("Basket weights must be positive, add up to 1 and target distinct assets other than the source")]
+    InvalidBasketWeights {},
+
+    #[error("{option} is not supported for basket DCAs")]
+    UnsupportedForBasket { option: String },
+
+    #[error("{option} is only supported for basket DCAs")]
+    BasketOnly { option: String },
+
+    #[error("Swap returned less than the minimum return")]
+    RouteSpreadExceeded {},
+
+    #[error("Swap of the dca returned nothing")]
+    NothingReceived {},
 }
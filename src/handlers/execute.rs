@@ -2,36 +2,54 @@
 
 use abstract_core::objects::{AssetEntry, DexName};
 use abstract_dex_adapter::msg::OfferAsset;
-use abstract_sdk::features::AbstractResponse;
+use abstract_sdk::features::{AbstractNameService, AbstractResponse};
+use abstract_sdk::Resolve;
 use cosmwasm_std::{
-    wasm_execute, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, Uint128,
+    wasm_execute, CosmosMsg, Decimal, Deps, DepsMut, Env, Fraction, MessageInfo, Order, Response,
+    StdError, StdResult, SubMsg, Uint128,
 };
-use cw_asset::{Asset, AssetList};
+use cw_asset::{Asset, AssetInfo, AssetList};
 
 use crate::contract::{AppResult, DCAApp};
 
 use crate::error::AppError;
-use crate::msg::{DCAExecuteMsg, ExecuteMsg, Frequency};
-use crate::state::{Config, DCAEntry, CONFIG, DCA_LIST, NEXT_ID};
+use crate::msg::{
+    DCABoundary, DCAExecuteMsg, DCAOptionKind, DCAOptions, DCAStrategy, ExecuteMsg, FailurePolicy,
+    Frequency, PostSwapAction,
+};
+use crate::replies::SWAP_REPLY_ID;
+use crate::state::{
+    Config, ConvertLeg, DCAEntry, DCAStats, DCAStatus, DCATarget, PendingConvert, CONFIG,
+    DCA_HISTORY, DCA_LIST, DCA_STATS, HALTED, HOOKS, NEXT_ID, PENDING_CONVERT, TASK_GAS_LIMITS,
+};
 use abstract_dex_adapter::api::DexInterface;
-use abstract_sdk::AbstractSdkResult;
+use abstract_sdk::TransferInterface;
 use croncat_app::croncat_integration_utils::{CronCatAction, CronCatTaskRequest};
 use croncat_app::{CronCat, CronCatInterface};
 
+/// Largest multiplier of a dip tier, a deep drop can't drain the source asset at once
+const MAX_DIP_MULTIPLIER: u128 = 10;
+
 /// Helper to for task creation message
+/// Remembers the gas limit of the task, later hook changes don't reach croncat
 fn create_convert_task_internal(
+    deps: DepsMut,
     env: Env,
+    app: &DCAApp,
     dca: DCAEntry,
     dca_id: String,
-    cron_cat: CronCat<DCAApp>,
-    config: Config,
-) -> AbstractSdkResult<CosmosMsg> {
+) -> AppResult<CosmosMsg> {
+    let config = CONFIG.load(deps.storage)?;
+    let hooks = HOOKS.query_hooks(deps.as_ref())?.hooks.len();
+    let gas_limit = dca.task_gas_limit(hooks);
+    TASK_GAS_LIMITS.save(deps.storage, dca_id.clone(), &gas_limit)?;
+    let boundary = DCABoundary::to_boundary(dca.start, dca.end)?;
     let interval = dca.frequency.to_interval();
     let task = CronCatTaskRequest {
         interval,
-        boundary: None,
-        // TODO?: should it be argument?
-        stop_on_fail: true,
+        boundary,
+        // Failed executions are handled by the failure policy of the dca
+        stop_on_fail: false,
         actions: vec![CronCatAction {
             msg: wasm_execute(
                 env.contract.address,
@@ -41,7 +59,7 @@ fn create_convert_task_internal(
                 vec![],
             )?
             .into(),
-            gas_limit: Some(300_000),
+            gas_limit: Some(gas_limit),
         }],
         queries: None,
         transforms: None,
@@ -52,7 +70,9 @@ fn create_convert_task_internal(
         config.dca_creation_amount,
     )])
     .into();
-    cron_cat.create_task(task, dca_id, assets)
+    app.cron_cat(deps.as_ref())
+        .create_task(task, dca_id, assets)
+        .map_err(Into::into)
 }
 
 pub fn execute_handler(
@@ -82,22 +102,46 @@ pub fn execute_handler(
             target_asset,
             frequency,
             dex,
+            options,
         } => create_dca(
             deps,
             env,
             info,
             app,
             source_asset,
-            target_asset,
+            DCATarget::Asset(target_asset),
             frequency,
             dex,
+            options,
+            "create_dca",
+        ),
+        DCAExecuteMsg::CreateBasketDCA {
+            source_asset,
+            targets,
+            frequency,
+            dex,
+            options,
+        } => create_dca(
+            deps,
+            env,
+            info,
+            app,
+            source_asset,
+            DCATarget::Basket(targets),
+            frequency,
+            dex,
+            options,
+            "create_basket_dca",
         ),
         DCAExecuteMsg::UpdateDCA {
             dca_id,
             new_source_asset,
             new_target_asset,
+            new_basket,
             new_frequency,
             new_dex,
+            new_options,
+            clear_options,
         } => update_dca(
             deps,
             env,
@@ -106,11 +150,42 @@ pub fn execute_handler(
             dca_id,
             new_source_asset,
             new_target_asset,
+            new_basket,
             new_frequency,
             new_dex,
+            new_options,
+            clear_options,
         ),
-        DCAExecuteMsg::CancelDCA { dca_id } => cancel_dca(deps, info, app, dca_id),
+        DCAExecuteMsg::CancelDCA { dca_id } => cancel_dca(deps, env, info, app, dca_id),
+        DCAExecuteMsg::PauseDCA { dca_id } => pause_dca(deps, env, info, app, dca_id),
+        DCAExecuteMsg::ResumeDCA { dca_id } => resume_dca(deps, env, info, app, dca_id),
+        DCAExecuteMsg::HaltAll { remove_tasks } => halt_all(deps, env, info, app, remove_tasks),
+        DCAExecuteMsg::UnhaltAll {} => unhalt_all(deps, info, app),
+        DCAExecuteMsg::AddHook { addr } => add_hook(deps, env, info, app, addr),
+        DCAExecuteMsg::RemoveHook { addr } => remove_hook(deps, env, info, app, addr),
         DCAExecuteMsg::Convert { dca_id } => convert(deps, env, info, app, dca_id),
+        DCAExecuteMsg::SwapWithMinReturn {
+            dex,
+            offer_asset,
+            ask_asset,
+            max_spread,
+            min_return,
+        } => swap_with_min_return(
+            deps,
+            env,
+            info,
+            app,
+            dex,
+            offer_asset,
+            ask_asset,
+            max_spread,
+            min_return,
+        ),
+        DCAExecuteMsg::AssertMinReturn {
+            asset,
+            balance_before,
+            min_return,
+        } => assert_min_return(deps, env, info, app, asset, balance_before, min_return),
     }
 }
 
@@ -141,47 +216,369 @@ fn update_config(
     Ok(app.tag_response(Response::default(), "update_config"))
 }
 
-/// Create new DCA
+/// Check dca parameters and simulate its swap
+fn validate_dca(deps: Deps, app: &DCAApp, dca: &DCAEntry) -> AppResult<()> {
+    if let Some(max_spread) = dca.max_spread {
+        if max_spread > Decimal::one() {
+            return Err(AppError::InvalidMaxSpread {});
+        }
+    }
+    if dca.total_budget == Some(Uint128::zero()) || dca.max_executions == Some(0) {
+        return Err(AppError::InvalidLimits {});
+    }
+    if let (Some(min_price), Some(max_price)) = (dca.min_price, dca.max_price) {
+        if min_price > max_price {
+            return Err(AppError::InvalidPriceRange {});
+        }
+    }
+    match &dca.target {
+        DCATarget::Asset(target_asset) => {
+            if let Some(route) = &dca.route {
+                if route.contains(&dca.source_asset.name) || route.contains(target_asset) {
+                    return Err(AppError::InvalidRoute {});
+                }
+            }
+        }
+        DCATarget::Basket(basket) => {
+            let unsupported = [
+                ("route", dca.route.is_some()),
+                ("max_price", dca.max_price.is_some()),
+                ("min_price", dca.min_price.is_some()),
+                ("dip_tiers", dca.dip_tiers.is_some()),
+                ("target_holding", dca.target_holding.is_some()),
+                ("post_swap", dca.post_swap.is_some()),
+                ("candidate_dexes", dca.candidate_dexes.is_some()),
+                (
+                    "strategy",
+                    matches!(
+                        dca.strategy,
+                        DCAStrategy::ValueAveraging { .. } | DCAStrategy::ExactOutput { .. }
+                    ),
+                ),
+            ];
+            if let Some((option, _)) = unsupported.iter().find(|(_, set)| *set) {
+                return Err(AppError::UnsupportedForBasket {
+                    option: option.to_string(),
+                });
+            }
+            validate_basket(&dca.source_asset.name, basket)?;
+        }
+    }
+    if let DCAStrategy::BalancePercentage {
+        percentage,
+        min_amount,
+        max_amount,
+    } = dca.strategy
+    {
+        if percentage.is_zero()
+            || percentage > Decimal::one()
+            || matches!((min_amount, max_amount), (Some(min), Some(max)) if min > max)
+        {
+            return Err(AppError::InvalidBalancePercentage {});
+        }
+    }
+    if let DCAStrategy::ValueAveraging { value_increment } = dca.strategy {
+        if value_increment.is_zero() {
+            return Err(AppError::InvalidValueIncrement {});
+        }
+    }
+    if let DCAStrategy::ExactOutput { amount, buffer } = dca.strategy {
+        if amount.is_zero() || buffer > Decimal::one() {
+            return Err(AppError::InvalidExactOutput {});
+        }
+    }
+    // Holdings of the proxy don't grow when the purchase is sent away or delegated
+    let tracks_holdings =
+        dca.target_holding.is_some() || matches!(dca.strategy, DCAStrategy::ValueAveraging { .. });
+    if dca.recipient.is_some() && tracks_holdings {
+        return Err(AppError::InvalidRecipient {});
+    }
+    if let (Some(PostSwapAction::Delegate { .. }), DCATarget::Asset(target_asset)) =
+        (&dca.post_swap, &dca.target)
+    {
+        if dca.recipient.is_some()
+            || tracks_holdings
+            || native_denom(deps, app, target_asset)?.is_none()
+        {
+            return Err(AppError::InvalidPostSwapAction {});
+        }
+    }
+    if let Some(dip_tiers) = &dca.dip_tiers {
+        if matches!(
+            dca.strategy,
+            DCAStrategy::ValueAveraging { .. } | DCAStrategy::ExactOutput { .. }
+        ) || dip_tiers.iter().any(|tier| {
+            tier.drop.is_zero()
+                || tier.drop >= Decimal::one()
+                || tier.multiplier.is_zero()
+                || tier.multiplier > Decimal::from_ratio(MAX_DIP_MULTIPLIER, 1_u128)
+        }) {
+            return Err(AppError::InvalidDipTiers {});
+        }
+    }
+    if dca.gas_limit == Some(0) {
+        return Err(AppError::InvalidGasLimit {});
+    }
+    if dca.failure_policy == (FailurePolicy::PauseAfter { failures: 0 }) {
+        return Err(AppError::InvalidFailurePolicy {});
+    }
+    DCABoundary::to_boundary(dca.start.clone(), dca.end.clone())?;
+
+    for (offer_asset, hops) in dca.split_offer(&dca.source_asset) {
+        if !offer_asset.amount.is_zero() {
+            simulate_route(deps, app, &dca.dex, offer_asset, &hops)?;
+        }
+    }
+    Ok(())
+}
+
+/// Check that basket weights are positive, add up to 1 and target distinct assets
+fn validate_basket(source_asset: &AssetEntry, basket: &[(AssetEntry, Decimal)]) -> AppResult<()> {
+    let mut assets: Vec<&AssetEntry> = vec![];
+    let mut total_weight = Decimal::zero();
+    for (asset, weight) in basket {
+        if weight.is_zero() || asset == source_asset || assets.contains(&asset) {
+            return Err(AppError::InvalidBasketWeights {});
+        }
+        assets.push(asset);
+        total_weight += weight;
+    }
+    if total_weight != Decimal::one() {
+        return Err(AppError::InvalidBasketWeights {});
+    }
+    Ok(())
+}
+
+/// Simulate swaps along the hops, returning the amount of the last asset
+pub(crate) fn simulate_route(
+    deps: Deps,
+    app: &DCAApp,
+    dex: &DexName,
+    offer_asset: OfferAsset,
+    hops: &[AssetEntry],
+) -> AppResult<Uint128> {
+    let dex = app.dex(deps, dex.clone());
+    let mut offer_asset = offer_asset;
+    for ask_asset in hops {
+        let simulation = dex.simulate_swap(offer_asset, ask_asset.clone())?;
+        offer_asset = OfferAsset::new(ask_asset.clone(), simulation.return_amount);
+    }
+    Ok(offer_asset.amount)
+}
+
+/// Denom of the asset, if it's a native token
+pub(crate) fn native_denom(
+    deps: Deps,
+    app: &DCAApp,
+    asset: &AssetEntry,
+) -> AppResult<Option<String>> {
+    let ans_host = app.ans_host(deps)?;
+    match asset.resolve(&deps.querier, &ans_host)? {
+        AssetInfo::Native(denom) => Ok(Some(denom)),
+        _ => Ok(None),
+    }
+}
+
+/// Value of the target asset holdings of the proxy, in source asset
+fn holdings_value(
+    deps: Deps,
+    app: &DCAApp,
+    dca: &DCAEntry,
+    target_asset: &AssetEntry,
+) -> AppResult<Uint128> {
+    let holdings = app.bank(deps).balance(target_asset)?.amount;
+    if holdings.is_zero() {
+        return Ok(Uint128::zero());
+    }
+    simulate_route(
+        deps,
+        app,
+        &dca.dex,
+        OfferAsset::new(target_asset.clone(), holdings),
+        &dca.reverse_hops(),
+    )
+}
+
+/// Amount of the target asset the proxy still lacks to reach the target holding
+pub(crate) fn holding_shortfall(
+    deps: Deps,
+    app: &DCAApp,
+    dca: &DCAEntry,
+) -> AppResult<Option<Uint128>> {
+    match (dca.target_holding, &dca.target) {
+        (Some(target_holding), DCATarget::Asset(target_asset)) => {
+            let holding = app.bank(deps).balance(target_asset)?.amount;
+            Ok(Some(target_holding.saturating_sub(holding)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Amount of the source asset needed to receive `amount` of the target asset
+fn required_offer(
+    deps: Deps,
+    app: &DCAApp,
+    dca: &DCAEntry,
+    target_asset: &AssetEntry,
+    amount: Uint128,
+) -> AppResult<Uint128> {
+    // Estimate by the reverse route, then correct by the forward simulation
+    let estimate = simulate_route(
+        deps,
+        app,
+        &dca.dex,
+        OfferAsset::new(target_asset.clone(), amount),
+        &dca.reverse_hops(),
+    )?;
+    let (_, hops) = dca.legs().remove(0);
+    let return_amount = simulate_route(
+        deps,
+        app,
+        &dca.dex,
+        OfferAsset::new(dca.source_asset.name.clone(), estimate),
+        &hops,
+    )?;
+    if return_amount.is_zero() {
+        return Err(AppError::NothingReceived {});
+    }
+    Ok(estimate.multiply_ratio(amount, return_amount))
+}
+
+/// Set the given options on the dca, options left out are kept
+fn apply_options(deps: Deps, dca: &mut DCAEntry, options: DCAOptions) -> AppResult<()> {
+    let DCAOptions {
+        total_budget,
+        max_executions,
+        start,
+        end,
+        max_spread,
+        route,
+        max_price,
+        min_price,
+        strategy,
+        dip_tiers,
+        target_holding,
+        recipient,
+        post_swap,
+        candidate_dexes,
+        fallback_dexes,
+        failure_policy,
+        gas_limit,
+    } = options;
+    if let Some(recipient) = recipient {
+        dca.recipient = Some(deps.api.addr_validate(&recipient)?);
+    }
+    if let Some(strategy) = strategy {
+        dca.strategy = strategy;
+    }
+    if let Some(failure_policy) = failure_policy {
+        dca.failure_policy = failure_policy;
+    }
+    dca.total_budget = total_budget.or(dca.total_budget);
+    dca.max_executions = max_executions.or(dca.max_executions);
+    dca.start = start.or(dca.start.take());
+    dca.end = end.or(dca.end.take());
+    dca.max_spread = max_spread.or(dca.max_spread);
+    dca.route = route.or(dca.route.take());
+    dca.max_price = max_price.or(dca.max_price);
+    dca.min_price = min_price.or(dca.min_price);
+    dca.dip_tiers = dip_tiers.or(dca.dip_tiers.take());
+    dca.target_holding = target_holding.or(dca.target_holding);
+    dca.post_swap = post_swap.or(dca.post_swap.take());
+    dca.candidate_dexes = candidate_dexes.or(dca.candidate_dexes.take());
+    dca.fallback_dexes = fallback_dexes.or(dca.fallback_dexes.take());
+    dca.gas_limit = gas_limit.or(dca.gas_limit);
+    Ok(())
+}
+
+/// Turn off the given settings of the dca, back to their defaults
+fn clear_options(dca: &mut DCAEntry, options: Vec<DCAOptionKind>) {
+    for option in options {
+        match option {
+            DCAOptionKind::TotalBudget => dca.total_budget = None,
+            DCAOptionKind::MaxExecutions => dca.max_executions = None,
+            DCAOptionKind::Start => dca.start = None,
+            DCAOptionKind::End => dca.end = None,
+            DCAOptionKind::MaxSpread => dca.max_spread = None,
+            DCAOptionKind::Route => dca.route = None,
+            DCAOptionKind::MaxPrice => dca.max_price = None,
+            DCAOptionKind::MinPrice => dca.min_price = None,
+            DCAOptionKind::Strategy => dca.strategy = DCAStrategy::default(),
+            DCAOptionKind::DipTiers => dca.dip_tiers = None,
+            DCAOptionKind::TargetHolding => dca.target_holding = None,
+            DCAOptionKind::Recipient => dca.recipient = None,
+            DCAOptionKind::PostSwap => dca.post_swap = None,
+            DCAOptionKind::CandidateDexes => dca.candidate_dexes = None,
+            DCAOptionKind::FallbackDexes => dca.fallback_dexes = None,
+            DCAOptionKind::FailurePolicy => dca.failure_policy = FailurePolicy::default(),
+            DCAOptionKind::GasLimit => dca.gas_limit = None,
+        }
+    }
+}
+
+/// Create new DCA, buying one asset or a basket of assets
 fn create_dca(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     app: DCAApp,
     source_asset: OfferAsset,
-    target_asset: AssetEntry,
+    target: DCATarget,
     frequency: Frequency,
     dex_name: DexName,
+    options: DCAOptions,
+    action: &str,
 ) -> AppResult {
     // Only the admin should be able to create dca
     app.admin.assert_admin(deps.as_ref(), &info.sender)?;
 
-    let config = CONFIG.load(deps.storage)?;
-
-    // Simulate swap first
-    app.dex(deps.as_ref(), dex_name.clone())
-        .simulate_swap(source_asset.clone(), target_asset.clone())?;
+    // No task is created during the halt, the dca is resumed once it's lifted
+    let halted = HALTED.may_load(deps.storage)?.unwrap_or_default();
+    let mut dca_entry = DCAEntry {
+        source_asset,
+        target,
+        frequency,
+        dex: dex_name,
+        total_budget: None,
+        max_executions: None,
+        start: None,
+        end: None,
+        max_spread: None,
+        route: None,
+        max_price: None,
+        min_price: None,
+        strategy: DCAStrategy::default(),
+        dip_tiers: None,
+        target_holding: None,
+        recipient: None,
+        post_swap: None,
+        candidate_dexes: None,
+        fallback_dexes: None,
+        failure_policy: FailurePolicy::default(),
+        gas_limit: None,
+        status: if halted {
+            DCAStatus::Paused
+        } else {
+            DCAStatus::Active
+        },
+    };
+    apply_options(deps.as_ref(), &mut dca_entry, options)?;
+    validate_dca(deps.as_ref(), &app, &dca_entry)?;
 
     // Generate DCA ID
     let id = NEXT_ID.update(deps.storage, |id| AppResult::Ok(id + 1))?;
     let dca_id = format!("dca_{id}");
 
-    let dca_entry = DCAEntry {
-        source_asset,
-        target_asset,
-        frequency,
-        dex: dex_name,
-    };
     DCA_LIST.save(deps.storage, dca_id.clone(), &dca_entry)?;
 
-    let cron_cat = app.cron_cat(deps.as_ref());
-    let task_msg = create_convert_task_internal(env, dca_entry, dca_id.clone(), cron_cat, config)?;
+    let mut response = Response::new().add_attribute("dca_id", dca_id.clone());
+    if !halted {
+        response = response.add_message(create_convert_task_internal(
+            deps, env, &app, dca_entry, dca_id,
+        )?);
+    }
 
-    Ok(app.tag_response(
-        Response::new()
-            .add_message(task_msg)
-            .add_attribute("dca_id", dca_id),
-        "create_dca",
-    ))
+    Ok(app.tag_response(response, action))
 }
 
 /// Update existing dca
@@ -193,33 +590,76 @@ fn update_dca(
     dca_id: String,
     new_source_asset: Option<OfferAsset>,
     new_target_asset: Option<AssetEntry>,
+    new_basket: Option<Vec<(AssetEntry, Decimal)>>,
     new_frequency: Option<Frequency>,
     new_dex: Option<DexName>,
+    new_options: DCAOptions,
+    clear_options: Vec<DCAOptionKind>,
 ) -> AppResult {
     app.admin.assert_admin(deps.as_ref(), &info.sender)?;
 
-    // Only if frequency is changed we have to re-create a task
-    let recreate_task = new_frequency.is_some();
-
     let old_dca = DCA_LIST.load(deps.storage, dca_id.clone())?;
-    let new_dca = DCAEntry {
-        source_asset: new_source_asset.unwrap_or(old_dca.source_asset),
-        target_asset: new_target_asset.unwrap_or(old_dca.target_asset),
-        frequency: new_frequency.unwrap_or(old_dca.frequency),
-        dex: new_dex.unwrap_or(old_dca.dex),
-    };
+    if old_dca.status_at(&env.block) == DCAStatus::Completed {
+        return Err(AppError::DCANotActive { dca_id });
+    }
+
+    // Only if schedule of a running dca is changed, or its gas limit is outdated, we have to re-create a task
+    let reschedule = new_frequency.is_some()
+        || new_options.start.is_some()
+        || new_options.end.is_some()
+        || clear_options
+            .iter()
+            .any(|option| matches!(option, DCAOptionKind::Start | DCAOptionKind::End));
 
-    // Simulate swap for a new dca
-    app.dex(deps.as_ref(), new_dca.dex.clone())
-        .simulate_swap(new_dca.source_asset.clone(), new_dca.target_asset.clone())?;
+    let halted = HALTED.may_load(deps.storage)?.unwrap_or_default();
+
+    let mut new_dca = old_dca;
+    if let Some(source_asset) = new_source_asset {
+        new_dca.source_asset = source_asset;
+    }
+    // The kind of target is kept, the stats of a single asset and a basket differ
+    match (&new_dca.target, new_target_asset, new_basket) {
+        (DCATarget::Basket(_), Some(_), _) => {
+            return Err(AppError::UnsupportedForBasket {
+                option: "new_target_asset".to_owned(),
+            })
+        }
+        (DCATarget::Asset(_), _, Some(_)) => {
+            return Err(AppError::BasketOnly {
+                option: "new_basket".to_owned(),
+            })
+        }
+        (_, Some(target_asset), _) => new_dca.target = DCATarget::Asset(target_asset),
+        (_, _, Some(basket)) => new_dca.target = DCATarget::Basket(basket),
+        _ => {}
+    }
+    if let Some(frequency) = new_frequency {
+        new_dca.frequency = frequency;
+    }
+    if let Some(dex) = new_dex {
+        new_dca.dex = dex;
+    }
+    clear_options(&mut new_dca, clear_options);
+    apply_options(deps.as_ref(), &mut new_dca, new_options)?;
+    validate_dca(deps.as_ref(), &app, &new_dca)?;
+    let hooks = HOOKS.query_hooks(deps.as_ref())?.hooks.len();
+    let recreate_task = new_dca.status == DCAStatus::Active
+        && (reschedule
+            || TASK_GAS_LIMITS.may_load(deps.storage, dca_id.clone())?
+                != Some(new_dca.task_gas_limit(hooks)));
+    // No task is created during the halt, the outdated task is removed and the dca is resumed once it's lifted
+    if recreate_task && halted {
+        new_dca.status = DCAStatus::Paused;
+    }
 
     DCA_LIST.save(deps.storage, dca_id.clone(), &new_dca)?;
 
-    let response = if recreate_task {
-        let config = CONFIG.load(deps.storage)?;
+    let response = if recreate_task && halted {
         let cron_cat = app.cron_cat(deps.as_ref());
-        let remove_task_msg = cron_cat.remove_task(dca_id.clone())?;
-        let create_task_msg = create_convert_task_internal(env, new_dca, dca_id, cron_cat, config)?;
+        Response::new().add_message(cron_cat.remove_task(dca_id)?)
+    } else if recreate_task {
+        let remove_task_msg = app.cron_cat(deps.as_ref()).remove_task(dca_id.clone())?;
+        let create_task_msg = create_convert_task_internal(deps, env, &app, new_dca, dca_id)?;
         Response::new().add_messages(vec![remove_task_msg, create_task_msg])
     } else {
         Response::new()
@@ -227,57 +667,790 @@ fn update_dca(
     Ok(app.tag_response(response, "update_dca"))
 }
 
-/// Remove existing dca, remove task from cron_cat
-fn cancel_dca(deps: DepsMut, info: MessageInfo, app: DCAApp, dca_id: String) -> AppResult {
+/// Mark dca as completed and remove its task from cron_cat
+pub(crate) fn complete_dca(
+    deps: DepsMut,
+    app: &DCAApp,
+    dca_id: String,
+    dca: DCAEntry,
+) -> AppResult<CosmosMsg> {
+    stop_dca(deps, app, dca_id, dca, DCAStatus::Completed)
+}
+
+/// Mark dca as failed and remove its task from cron_cat
+pub(crate) fn fail_dca(
+    deps: DepsMut,
+    app: &DCAApp,
+    dca_id: String,
+    dca: DCAEntry,
+) -> AppResult<CosmosMsg> {
+    stop_dca(deps, app, dca_id, dca, DCAStatus::Failed)
+}
+
+/// Apply the failure policy of the dca after a failed execution
+/// Stop fails the dca on the first failure, otherwise it goes on like a skipped execution
+pub(crate) fn apply_failure_policy(
+    deps: DepsMut,
+    env: &Env,
+    app: &DCAApp,
+    dca_id: String,
+    dca: DCAEntry,
+    refill: bool,
+    response: Response,
+) -> AppResult<Response> {
+    let stats = DCA_STATS.load(deps.storage, dca_id.clone())?;
+    let max_failures = match dca.failure_policy {
+        FailurePolicy::Stop => Some(1),
+        FailurePolicy::Skip => None,
+        FailurePolicy::PauseAfter { failures } => Some(failures),
+    };
+    if max_failures.map_or(false, |failures| stats.consecutive_failures >= failures) {
+        return Ok(response
+            .add_message(fail_dca(deps, app, dca_id, dca)?)
+            .add_attribute("failed", "true"));
+    }
+    // Nothing is scheduled after the last run, a failed last run completes the dca
+    if dca.is_exhausted(&stats) || dca.is_final_run(&env.block) {
+        return Ok(response
+            .add_message(complete_dca(deps, app, dca_id, dca)?)
+            .add_attribute("completed", "true"));
+    }
+    if !refill {
+        return Ok(response);
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let cron_cat = app.cron_cat(deps.as_ref());
+    Ok(response.add_message(refill_task_msg(&cron_cat, dca_id, &config)?))
+}
+
+fn stop_dca(
+    deps: DepsMut,
+    app: &DCAApp,
+    dca_id: String,
+    mut dca: DCAEntry,
+    status: DCAStatus,
+) -> AppResult<CosmosMsg> {
+    dca.status = status;
+    DCA_LIST.save(deps.storage, dca_id.clone(), &dca)?;
+
+    let cron_cat = app.cron_cat(deps.as_ref());
+    cron_cat.remove_task(dca_id).map_err(Into::into)
+}
+
+/// Remove existing dca with its stats and history, remove task from cron_cat
+fn cancel_dca(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    app: DCAApp,
+    dca_id: String,
+) -> AppResult {
     app.admin.assert_admin(deps.as_ref(), &info.sender)?;
 
+    let dca = DCA_LIST.load(deps.storage, dca_id.clone())?;
     DCA_LIST.remove(deps.storage, dca_id.clone());
+    DCA_STATS.remove(deps.storage, dca_id.clone());
+    TASK_GAS_LIMITS.remove(deps.storage, dca_id.clone());
+    let executions = DCA_HISTORY
+        .prefix(dca_id.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for execution in executions {
+        DCA_HISTORY.remove(deps.storage, (dca_id.clone(), execution));
+    }
+
+    // Paused, completed and ended dcas have no task left
+    let response = if dca.status_at(&env.block) == DCAStatus::Active {
+        let cron_cat = app.cron_cat(deps.as_ref());
+        Response::new().add_message(cron_cat.remove_task(dca_id)?)
+    } else {
+        Response::new()
+    };
+
+    Ok(app.tag_response(response, "cancel_dca"))
+}
+
+/// Pause existing dca, remove task from cron_cat
+/// Remaining task balance is refunded by cron_cat
+fn pause_dca(deps: DepsMut, env: Env, info: MessageInfo, app: DCAApp, dca_id: String) -> AppResult {
+    app.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut dca = DCA_LIST.load(deps.storage, dca_id.clone())?;
+    if dca.status_at(&env.block) != DCAStatus::Active {
+        return Err(AppError::DCANotActive { dca_id });
+    }
+    dca.status = DCAStatus::Paused;
+    DCA_LIST.save(deps.storage, dca_id.clone(), &dca)?;
 
     let cron_cat = app.cron_cat(deps.as_ref());
     let remove_task_msg = cron_cat.remove_task(dca_id)?;
 
-    Ok(app.tag_response(Response::new().add_message(remove_task_msg), "cancel_dca"))
+    Ok(app.tag_response(Response::new().add_message(remove_task_msg), "pause_dca"))
+}
+
+/// Resume paused or failed dca, re-create its task
+fn resume_dca(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    app: DCAApp,
+    dca_id: String,
+) -> AppResult {
+    app.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if HALTED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(AppError::Halted {});
+    }
+
+    let mut dca = DCA_LIST.load(deps.storage, dca_id.clone())?;
+    if !matches!(dca.status, DCAStatus::Paused | DCAStatus::Failed) {
+        return Err(AppError::DCANotPaused { dca_id });
+    }
+    dca.status = DCAStatus::Active;
+    DCA_LIST.save(deps.storage, dca_id.clone(), &dca)?;
+
+    // Failures are counted from scratch
+    DCA_STATS.update(deps.storage, dca_id.clone(), |stats| {
+        let mut stats = stats.unwrap_or_default();
+        stats.consecutive_failures = 0;
+        StdResult::Ok(stats)
+    })?;
+
+    let task_msg = create_convert_task_internal(deps, env, &app, dca, dca_id)?;
+
+    Ok(app.tag_response(Response::new().add_message(task_msg), "resume_dca"))
+}
+
+/// Halt all dcas, optionally removing tasks of the active ones
+fn halt_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    app: DCAApp,
+    remove_tasks: bool,
+) -> AppResult {
+    app.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    HALTED.save(deps.storage, &true)?;
+
+    let mut messages = vec![];
+    if remove_tasks {
+        let active_dcas = DCA_LIST
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter(|entry| {
+                entry.as_ref().map_or(true, |(_, dca)| {
+                    dca.status_at(&env.block) == DCAStatus::Active
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let cron_cat = app.cron_cat(deps.as_ref());
+        messages = active_dcas
+            .iter()
+            .map(|(dca_id, _)| cron_cat.remove_task(dca_id.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (dca_id, mut dca) in active_dcas {
+            dca.status = DCAStatus::Paused;
+            DCA_LIST.save(deps.storage, dca_id, &dca)?;
+        }
+    }
+
+    Ok(app.tag_response(Response::new().add_messages(messages), "halt_all"))
+}
+
+/// Lift the halt, paused dcas have to be resumed one by one
+fn unhalt_all(deps: DepsMut, info: MessageInfo, app: DCAApp) -> AppResult {
+    app.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    HALTED.save(deps.storage, &false)?;
+
+    Ok(app.tag_response(Response::new(), "unhalt_all"))
+}
+
+/// Register a contract notified after every dca execution
+fn add_hook(deps: DepsMut, env: Env, info: MessageInfo, app: DCAApp, addr: String) -> AppResult {
+    app.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let hook = deps.api.addr_validate(&addr)?;
+    HOOKS.add_hook(deps.storage, hook)?;
+
+    let messages = recreate_outdated_tasks(deps, &env, &app)?;
+    Ok(app.tag_response(
+        Response::new()
+            .add_messages(messages)
+            .add_attribute("hook", addr),
+        "add_hook",
+    ))
+}
+
+/// Unregister a hook contract
+fn remove_hook(deps: DepsMut, env: Env, info: MessageInfo, app: DCAApp, addr: String) -> AppResult {
+    app.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let hook = deps.api.addr_validate(&addr)?;
+    HOOKS.remove_hook(deps.storage, hook)?;
+
+    let messages = recreate_outdated_tasks(deps, &env, &app)?;
+    Ok(app.tag_response(
+        Response::new()
+            .add_messages(messages)
+            .add_attribute("hook", addr),
+        "remove_hook",
+    ))
+}
+
+/// Recreate the tasks of active dcas whose gas limit is outdated, e.g. after the hooks changed
+/// During the halt the outdated tasks are only removed and their dcas paused, as on update
+fn recreate_outdated_tasks(
+    mut deps: DepsMut,
+    env: &Env,
+    app: &DCAApp,
+) -> AppResult<Vec<CosmosMsg>> {
+    let halted = HALTED.may_load(deps.storage)?.unwrap_or_default();
+    let hooks = HOOKS.query_hooks(deps.as_ref())?.hooks.len();
+    let active_dcas = DCA_LIST
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|entry| {
+            entry.as_ref().map_or(true, |(_, dca)| {
+                dca.status_at(&env.block) == DCAStatus::Active
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut messages = vec![];
+    for (dca_id, mut dca) in active_dcas {
+        if TASK_GAS_LIMITS.may_load(deps.storage, dca_id.clone())?
+            == Some(dca.task_gas_limit(hooks))
+        {
+            continue;
+        }
+        messages.push(app.cron_cat(deps.as_ref()).remove_task(dca_id.clone())?);
+        if halted {
+            dca.status = DCAStatus::Paused;
+            DCA_LIST.save(deps.storage, dca_id, &dca)?;
+        } else {
+            messages.push(create_convert_task_internal(
+                deps.branch(),
+                env.clone(),
+                app,
+                dca,
+                dca_id,
+            )?);
+        }
+    }
+    Ok(messages)
+}
+
+/// Next execution of a dca, as sized by the strategy, price guards and budget caps
+pub(crate) enum ConvertPlan {
+    /// The dca ended, spent its budget or reached its target holding
+    Complete,
+    /// The execution is skipped without failing the task
+    Skip {
+        reason: &'static str,
+        price: Option<Decimal>,
+    },
+    /// Swap the legs on the dex
+    Swap {
+        dex: DexName,
+        max_spread: Decimal,
+        legs: Vec<ConvertLeg>,
+        fallback_dexes: Vec<DexName>,
+        /// Price of the target asset, if the price guard simulated the route
+        price: Option<Decimal>,
+        /// Caps that shortened the swap
+        capped_by: Vec<&'static str>,
+    },
+}
+
+/// Whether the task balance dropped below the refill threshold
+pub(crate) fn needs_refill(
+    cron_cat: &CronCat<DCAApp>,
+    env: &Env,
+    dca_id: &str,
+    config: &Config,
+) -> AppResult<bool> {
+    // A task without a balance is gone, there is nothing to refill
+    let task_balance = cron_cat
+        .query_task_balance(env.contract.address.clone(), dca_id.to_owned())?
+        .balance;
+    Ok(task_balance.map_or(false, |balance| {
+        balance.native_balance < config.refill_threshold
+    }))
+}
+
+/// Refill the task with the creation amount, paid by the proxy
+pub(crate) fn refill_task_msg(
+    cron_cat: &CronCat<DCAApp>,
+    dca_id: String,
+    config: &Config,
+) -> AppResult<CosmosMsg> {
+    let assets = AssetList::from(vec![Asset::native(
+        config.native_denom.clone(),
+        config.dca_creation_amount,
+    )])
+    .into();
+    cron_cat.refill_task(dca_id, assets).map_err(Into::into)
+}
+
+/// Size the next execution of the dca, without changing any state
+/// Value averaging counts the new period on the passed stats
+pub(crate) fn plan_convert(
+    deps: Deps,
+    env: &Env,
+    app: &DCAApp,
+    config: &Config,
+    mut dca: DCAEntry,
+    stats: &mut DCAStats,
+) -> AppResult<ConvertPlan> {
+    // Clean up the dca if it's called after its end, with nothing left to spend
+    // or with the target holding reached
+    let shortfall = holding_shortfall(deps, app, &dca)?;
+    if dca.has_ended(&env.block)
+        || dca.is_exhausted(stats)
+        || shortfall.map_or(false, |shortfall| shortfall.is_zero())
+    {
+        return Ok(ConvertPlan::Complete);
+    }
+
+    let mut capped_by = vec![];
+
+    // Pick the dex with the best return first, the sizing simulations run on it
+    // Dexes that can't simulate the route are left out
+    if let Some(candidate_dexes) = &dca.candidate_dexes {
+        let mut probe = dca.source_asset.clone();
+        if probe.amount.is_zero() {
+            probe.amount = app.bank(deps).balance(&probe.name)?.amount;
+        }
+        // Candidate dexes are rejected for baskets, the single leg is quoted
+        let (_, hops) = dca.legs().remove(0);
+        let mut quotes = vec![];
+        if !probe.amount.is_zero() {
+            for dex in std::iter::once(&dca.dex).chain(candidate_dexes) {
+                if let Ok(return_amount) = simulate_route(deps, app, dex, probe.clone(), &hops) {
+                    quotes.push((dex.clone(), return_amount));
+                }
+            }
+        }
+        dca.dex = dca.best_dex(quotes);
+    }
+
+    // Size the purchase by the strategy of the dca
+    match (&dca.strategy, &dca.target) {
+        // Buy only the shortfall of the value averaging target, up to the source asset amount
+        (DCAStrategy::ValueAveraging { value_increment }, DCATarget::Asset(target_asset)) => {
+            stats.periods += 1;
+
+            let target_value = value_increment
+                .checked_mul(stats.periods.into())
+                .map_err(StdError::from)?;
+            let shortfall =
+                target_value.saturating_sub(holdings_value(deps, app, &dca, target_asset)?);
+            if shortfall.is_zero() {
+                return Ok(ConvertPlan::Skip {
+                    reason: "target_value_reached",
+                    price: None,
+                });
+            }
+            if shortfall < dca.source_asset.amount {
+                capped_by.push("target_value");
+                dca.source_asset.amount = shortfall;
+            }
+        }
+        // Spend a share of what the proxy holds, within the bounds
+        (
+            DCAStrategy::BalancePercentage {
+                percentage,
+                min_amount,
+                max_amount,
+            },
+            _,
+        ) => {
+            let balance = app.bank(deps).balance(&dca.source_asset.name)?.amount;
+            dca.source_asset.amount =
+                balance_percentage_bounds(balance * *percentage, *min_amount, *max_amount, balance);
+        }
+        // Offer what the simulation asks for, raised by the buffer
+        // The dca is done once its budget can't cover a purchase, a short balance may be topped up
+        (DCAStrategy::ExactOutput { amount, buffer }, DCATarget::Asset(target_asset)) => {
+            let required = required_offer(deps, app, &dca, target_asset, *amount)?;
+            let required = required * (Decimal::one() + *buffer);
+            if dca
+                .remaining_budget(stats)
+                .map_or(false, |remaining| required > remaining)
+            {
+                return Ok(ConvertPlan::Complete);
+            }
+            let balance = app.bank(deps).balance(&dca.source_asset.name)?.amount;
+            if required > balance {
+                return Ok(ConvertPlan::Skip {
+                    reason: "balance_too_low",
+                    price: None,
+                });
+            }
+            dca.source_asset.amount = required;
+        }
+        _ => {}
+    }
+
+    // Buy more when the price dropped since the last execution
+    if let (Some(last_price), Some(_)) = (stats.last_price, &dca.dip_tiers) {
+        if !dca.source_asset.amount.is_zero() {
+            let (offer_asset, hops) = dca.split_offer(&dca.source_asset).remove(0);
+            let return_amount = simulate_route(deps, app, &dca.dex, offer_asset, &hops)?;
+            let price = Decimal::checked_from_ratio(dca.source_asset.amount, return_amount)
+                .unwrap_or(Decimal::MAX);
+            let multiplier = dca.dip_multiplier(last_price, price);
+            let amount = dca
+                .source_asset
+                .amount
+                .checked_multiply_ratio(multiplier.numerator(), multiplier.denominator())
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+            // The multiplied purchase stays within the bounds of the strategy
+            dca.source_asset.amount = match &dca.strategy {
+                DCAStrategy::BalancePercentage {
+                    min_amount,
+                    max_amount,
+                    ..
+                } => {
+                    let balance = app.bank(deps).balance(&dca.source_asset.name)?.amount;
+                    balance_percentage_bounds(amount, *min_amount, *max_amount, balance)
+                }
+                // Only the multiplied part is held back by the balance
+                _ => {
+                    let balance = app.bank(deps).balance(&dca.source_asset.name)?.amount;
+                    let cap = balance.max(dca.source_asset.amount);
+                    if cap < amount {
+                        capped_by.push("balance");
+                        cap
+                    } else {
+                        amount
+                    }
+                }
+            };
+        }
+    }
+
+    // Shrink the swap to what is left of the budget
+    if let Some(remaining) = dca.remaining_budget(stats) {
+        if remaining < dca.source_asset.amount {
+            capped_by.push("budget");
+            dca.source_asset.amount = remaining;
+        }
+    }
+
+    // Don't buy much past the target holding, the spread allowance covers the rounding
+    if let (Some(shortfall), DCATarget::Asset(target_asset)) = (shortfall, &dca.target) {
+        let required = required_offer(deps, app, &dca, target_asset, shortfall)?;
+        let max_spread = dca.max_spread.unwrap_or(config.max_spread);
+        let cap = required * (Decimal::one() + max_spread);
+        if cap < dca.source_asset.amount {
+            capped_by.push("target_holding");
+            dca.source_asset.amount = cap;
+        }
+    }
+
+    // Legs too small to get a share of the source asset are left out
+    let legs = dca
+        .split_offer(&dca.source_asset)
+        .into_iter()
+        .filter(|(offer_asset, _)| !offer_asset.amount.is_zero())
+        .collect::<Vec<_>>();
+    if legs.is_empty() {
+        return Ok(ConvertPlan::Skip {
+            reason: "nothing_to_offer",
+            price: None,
+        });
+    }
+
+    // Simulate the route, if the price guard or a multi-hop route needs it
+    // Both are only allowed for a single target asset, so there is one leg
+    let simulated_return =
+        if dca.route.is_some() || dca.max_price.is_some() || dca.min_price.is_some() {
+            let (offer_asset, hops) = legs[0].clone();
+            Some(simulate_route(deps, app, &dca.dex, offer_asset, &hops)?)
+        } else {
+            None
+        };
+
+    // Skip the purchase if the price is out of bounds, without failing the task
+    let price = simulated_return.map(|return_amount| {
+        Decimal::checked_from_ratio(dca.source_asset.amount, return_amount).unwrap_or(Decimal::MAX)
+    });
+    if let Some(price) = price {
+        if let Some(reason) = dca.price_guard(price) {
+            return Ok(ConvertPlan::Skip {
+                reason,
+                price: Some(price),
+            });
+        }
+    }
+
+    // Slippage of a multi-hop route is checked on the final amount
+    // Exact output has to receive at least the requested amount, or the shortfall it was capped to
+    let max_spread = dca.max_spread.unwrap_or(config.max_spread);
+    let min_return = match dca.strategy {
+        DCAStrategy::ExactOutput { amount, .. } => match shortfall {
+            Some(shortfall) if capped_by.contains(&"target_holding") => Some(amount.min(shortfall)),
+            _ => Some(amount),
+        },
+        _ => simulated_return
+            .filter(|_| dca.route.is_some())
+            .map(|return_amount| return_amount * Decimal::one().saturating_sub(max_spread)),
+    };
+
+    let legs = legs
+        .into_iter()
+        .map(|(offered, hops)| ConvertLeg {
+            offered,
+            hops,
+            min_return,
+        })
+        .collect();
+    Ok(ConvertPlan::Swap {
+        dex: dca.dex,
+        max_spread,
+        legs,
+        fallback_dexes: dca.fallback_dexes.unwrap_or_default(),
+        price,
+        capped_by,
+    })
+}
+
+/// Keep a balance percentage purchase within its bounds and the balance
+fn balance_percentage_bounds(
+    amount: Uint128,
+    min_amount: Option<Uint128>,
+    max_amount: Option<Uint128>,
+    balance: Uint128,
+) -> Uint128 {
+    let mut amount = amount;
+    if let Some(max_amount) = max_amount {
+        amount = amount.min(max_amount);
+    }
+    if let Some(min_amount) = min_amount {
+        amount = amount.max(min_amount);
+    }
+    amount.min(balance)
 }
 
 /// Execute swap if called my croncat manager
 /// Refill task if needed
 fn convert(deps: DepsMut, env: Env, info: MessageInfo, app: DCAApp, dca_id: String) -> AppResult {
+    // Tasks kept during the halt keep firing, skip without failing them
+    if HALTED.may_load(deps.storage)?.unwrap_or_default() {
+        return Ok(app.tag_response(
+            Response::new()
+                .add_attribute("dca_id", dca_id)
+                .add_attribute("skipped", "halted"),
+            "convert",
+        ));
+    }
+
     let config = CONFIG.load(deps.storage)?;
     let dca = DCA_LIST.load(deps.storage, dca_id.clone())?;
+    if dca.status != DCAStatus::Active {
+        return Err(AppError::DCANotActive { dca_id });
+    }
 
-    let cron_cat = app.cron_cat(deps.as_ref());
+    let refill = {
+        let cron_cat = app.cron_cat(deps.as_ref());
+        let manager_addr =
+            cron_cat.query_manager_addr(env.contract.address.clone(), dca_id.clone())?;
+        if manager_addr != info.sender {
+            return Err(AppError::NotManagerConvert {});
+        }
+        // In case task running out of balance - refill it
+        // A swap refills from its reply, the refill would skew the balances the swap is measured by
+        needs_refill(&cron_cat, &env, &dca_id, &config)?
+    };
 
-    let manager_addr = cron_cat.query_manager_addr(env.contract.address.clone(), dca_id.clone())?;
-    if manager_addr != info.sender {
-        return Err(AppError::NotManagerConvert {});
+    let mut stats = DCA_STATS
+        .may_load(deps.storage, dca_id.clone())?
+        .unwrap_or_default();
+    let periods = stats.periods;
+    let plan = match plan_convert(deps.as_ref(), &env, &app, &config, dca.clone(), &mut stats) {
+        Ok(plan) => plan,
+        // An execution that can't be planned fails like a failed swap
+        Err(error) => {
+            DCA_STATS.update(deps.storage, dca_id.clone(), |stats| {
+                let mut stats = stats.unwrap_or_default();
+                stats.consecutive_failures += 1;
+                AppResult::Ok(stats)
+            })?;
+            let response = Response::new()
+                .add_attribute("dca_id", dca_id.clone())
+                .add_attribute("convert_error", error.to_string());
+            let response = apply_failure_policy(deps, &env, &app, dca_id, dca, refill, response)?;
+            return Ok(app.tag_response(response, "convert"));
+        }
+    };
+    if stats.periods != periods {
+        DCA_STATS.save(deps.storage, dca_id.clone(), &stats)?;
     }
-    let mut messages = vec![];
 
-    // In case task running out of balance - refill it
-    let task_balance = cron_cat
-        .query_task_balance(env.contract.address, dca_id.clone())?
-        .balance
-        .unwrap();
-    if task_balance.native_balance < config.refill_threshold {
-        messages.push(
-            cron_cat.refill_task(
-                dca_id,
-                AssetList::from(vec![Asset::native(
-                    config.native_denom,
-                    config.dca_creation_amount,
-                )])
-                .into(),
-            )?,
-        );
-    }
-
-    // TODO: remove dca on failed swap?
-    // Or `stop_on_fail` should be enough
-    messages.push(app.dex(deps.as_ref(), dca.dex).swap(
-        dca.source_asset,
-        dca.target_asset,
-        Some(config.max_spread),
-        None,
-    )?);
-    Ok(app.tag_response(Response::new().add_messages(messages), "convert"))
+    let (dex, max_spread, mut legs, fallback_dexes) = match plan {
+        ConvertPlan::Complete => {
+            let remove_task_msg = complete_dca(deps, &app, dca_id.clone(), dca)?;
+            return Ok(app.tag_response(
+                Response::new()
+                    .add_message(remove_task_msg)
+                    .add_attribute("dca_id", dca_id),
+                "complete_dca",
+            ));
+        }
+        ConvertPlan::Skip { reason, price } => {
+            let mut response = Response::new().add_attribute("dca_id", dca_id.clone());
+            // Nothing is scheduled after the last run, a skipped last run completes the dca
+            if dca.is_final_run(&env.block) {
+                response = response
+                    .add_message(complete_dca(deps, &app, dca_id, dca)?)
+                    .add_attribute("completed", "true");
+            } else if refill {
+                let cron_cat = app.cron_cat(deps.as_ref());
+                response = response.add_message(refill_task_msg(&cron_cat, dca_id, &config)?);
+            }
+            if let Some(price) = price {
+                response = response.add_attribute("price", price.to_string());
+            }
+            return Ok(app.tag_response(response.add_attribute("skipped", reason), "convert"));
+        }
+        ConvertPlan::Swap {
+            dex,
+            max_spread,
+            legs,
+            fallback_dexes,
+            ..
+        } => (dex, max_spread, legs, fallback_dexes),
+    };
+    let leg = legs.remove(0);
+    let offer_asset = leg.offered.clone();
+
+    // A failed swap is retried on the fallback dexes before the execution fails
+    let swap_msg = swap_next_hop(
+        deps,
+        &env,
+        &app,
+        PendingConvert {
+            dca_id,
+            dex: dex.clone(),
+            max_spread,
+            leg,
+            offer_asset: offer_asset.clone(),
+            fallback_dexes,
+            ask_balance_before: Uint128::zero(),
+            next_legs: legs,
+            swaps: vec![],
+            refill,
+        },
+        offer_asset,
+    )?;
+    Ok(app.tag_response(
+        Response::new()
+            .add_submessage(swap_msg)
+            .add_attribute("dex", dex),
+        "convert",
+    ))
+}
+
+/// Swap into the next hop of the current leg
+/// Remembers the ask balance, so the reply can measure what the swap returned
+pub(crate) fn swap_next_hop(
+    deps: DepsMut,
+    env: &Env,
+    app: &DCAApp,
+    mut pending: PendingConvert,
+    offer_asset: OfferAsset,
+) -> AppResult<SubMsg> {
+    let ask_asset = pending.leg.hops[0].clone();
+    pending.ask_balance_before = app.bank(deps.as_ref()).balance(&ask_asset)?.amount;
+    pending.offer_asset = offer_asset.clone();
+    PENDING_CONVERT.save(deps.storage, &pending)?;
+
+    let swap_msg = match pending.leg.min_return {
+        // Slippage of the route is checked within the last swap, a short return fails the swap
+        Some(min_return) if pending.leg.hops.len() == 1 => wasm_execute(
+            env.contract.address.clone(),
+            &ExecuteMsg::from(DCAExecuteMsg::SwapWithMinReturn {
+                dex: pending.dex,
+                offer_asset,
+                ask_asset,
+                max_spread: pending.max_spread,
+                min_return,
+            }),
+            vec![],
+        )?
+        .into(),
+        _ => app.dex(deps.as_ref(), pending.dex).swap(
+            offer_asset,
+            ask_asset,
+            Some(pending.max_spread),
+            None,
+        )?,
+    };
+    Ok(SubMsg::reply_always(swap_msg, SWAP_REPLY_ID))
+}
+
+/// Swap on the dex, followed by the check of what it returned
+fn swap_with_min_return(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    app: DCAApp,
+    dex: DexName,
+    offer_asset: OfferAsset,
+    ask_asset: AssetEntry,
+    max_spread: Decimal,
+    min_return: Uint128,
+) -> AppResult {
+    if info.sender != env.contract.address {
+        return Err(AppError::NotSelf {});
+    }
+
+    let balance_before = app.bank(deps.as_ref()).balance(&ask_asset)?.amount;
+    let swap_msg =
+        app.dex(deps.as_ref(), dex)
+            .swap(offer_asset, ask_asset.clone(), Some(max_spread), None)?;
+    let assert_msg = wasm_execute(
+        env.contract.address,
+        &ExecuteMsg::from(DCAExecuteMsg::AssertMinReturn {
+            asset: ask_asset,
+            balance_before,
+            min_return,
+        }),
+        vec![],
+    )?;
+
+    Ok(app.tag_response(
+        Response::new()
+            .add_message(swap_msg)
+            .add_message(assert_msg),
+        "swap_with_min_return",
+    ))
+}
+
+/// Fail if the proxy received less than the minimum return since the balance was taken
+fn assert_min_return(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    app: DCAApp,
+    asset: AssetEntry,
+    balance_before: Uint128,
+    min_return: Uint128,
+) -> AppResult {
+    if info.sender != env.contract.address {
+        return Err(AppError::NotSelf {});
+    }
+
+    let balance = app.bank(deps.as_ref()).balance(&asset)?.amount;
+    let received = balance.saturating_sub(balance_before);
+    if received < min_return {
+        return Err(AppError::RouteSpreadExceeded {});
+    }
+
+    Ok(app.tag_response(
+        Response::new().add_attribute("received", received),
+        "assert_min_return",
+    ))
 }
@@ -2,7 +2,7 @@ use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
 
 use crate::contract::{AppResult, DCAApp};
 use crate::msg::AppInstantiateMsg;
-use crate::state::{Config, CONFIG, NEXT_ID};
+use crate::state::{Config, CONFIG, HALTED, NEXT_ID};
 
 pub fn instantiate_handler(
     deps: DepsMut,
@@ -20,6 +20,7 @@ pub fn instantiate_handler(
 
     CONFIG.save(deps.storage, &config)?;
     NEXT_ID.save(deps.storage, &0)?;
+    HALTED.save(deps.storage, &false)?;
     // Example instantiation that doesn't do anything
     Ok(Response::new())
 }
@@ -1,40 +1,368 @@
 use crate::contract::{AppResult, DCAApp};
-use crate::msg::{ConfigResponse, DCAQueryMsg, DCAResponse};
-use crate::state::{CONFIG, DCA_LIST};
-use abstract_core::objects::DexAssetPairing;
-use abstract_sdk::features::AbstractNameService;
-use abstract_sdk::Resolve;
-use cosmwasm_std::{to_binary, Binary, Deps, Env, StdResult};
-
-pub fn query_handler(deps: Deps, _env: Env, app: &DCAApp, msg: DCAQueryMsg) -> AppResult<Binary> {
+use crate::error::AppError;
+use crate::handlers::execute::{native_denom, needs_refill, plan_convert, ConvertPlan};
+use crate::msg::{
+    ConfigResponse, DCAHealthResponse, DCAHistoryResponse, DCAListItem, DCAListResponse,
+    DCAQueryMsg, DCAResponse, DCAStatsResponse, SimulateConvertResponse, SimulatedLeg,
+};
+use crate::state::{
+    ConvertLeg, DCAEntry, DCAStatus, CONFIG, DCA_HISTORY, DCA_LIST, DCA_STATS, HALTED, HOOKS,
+    TASK_GAS_LIMITS,
+};
+use abstract_core::objects::ans_host::AnsHost;
+use abstract_core::objects::{DexAssetPairing, DexName, PoolReference};
+use abstract_dex_adapter::api::DexInterface;
+use abstract_dex_adapter::msg::OfferAsset;
+use abstract_sdk::features::{AbstractNameService, AccountIdentification};
+use abstract_sdk::{Resolve, TransferInterface};
+use cosmwasm_std::{to_binary, Binary, Deps, Env, Order, StdError, StdResult, Uint128};
+use croncat_app::CronCatInterface;
+use cw_storage_plus::Bound;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn query_handler(deps: Deps, env: Env, app: &DCAApp, msg: DCAQueryMsg) -> AppResult<Binary> {
     match msg {
         DCAQueryMsg::Config {} => to_binary(&query_config(deps)?),
-        DCAQueryMsg::DCA { dca_id } => to_binary(&query_dca(deps, app, dca_id)?),
+        DCAQueryMsg::DCA { dca_id } => to_binary(&query_dca(deps, &env, app, dca_id)?),
+        DCAQueryMsg::ListDCAs {
+            start_after,
+            limit,
+            with_pool_references,
+        } => to_binary(&query_dca_list(
+            deps,
+            &env,
+            app,
+            start_after,
+            limit,
+            with_pool_references.unwrap_or_default(),
+        )?),
+        DCAQueryMsg::DCAHistory {
+            dca_id,
+            start_after,
+            limit,
+        } => to_binary(&query_dca_history(deps, dca_id, start_after, limit)?),
+        DCAQueryMsg::DCAStats { dca_id } => to_binary(&query_dca_stats(deps, dca_id)?),
+        DCAQueryMsg::Hooks {} => to_binary(&HOOKS.query_hooks(deps)?),
+        DCAQueryMsg::SimulateConvert { dca_id } => {
+            to_binary(&query_simulate_convert(deps, env, app, dca_id)?)
+        }
+        DCAQueryMsg::DCAHealth { dca_id } => to_binary(&query_dca_health(deps, env, app, dca_id)?),
     }
     .map_err(Into::into)
 }
 
 fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
-    Ok(ConfigResponse { config })
+    let halted = HALTED.may_load(deps.storage)?.unwrap_or_default();
+    Ok(ConfigResponse { config, halted })
 }
 
-/// Get dca
-fn query_dca(deps: Deps, app: &DCAApp, dca_id: String) -> AppResult<DCAResponse> {
-    let dca = DCA_LIST.may_load(deps.storage, dca_id)?;
+/// Resolve pool references of every hop of every leg of the dca
+fn dca_pool_references(
+    deps: Deps,
+    ans_host: &AnsHost,
+    dca: &DCAEntry,
+) -> AppResult<Vec<PoolReference>> {
+    let mut pool_references = vec![];
+    for (_, hops) in dca.legs() {
+        let mut offer_asset = dca.source_asset.name.clone();
+        for ask_asset in hops {
+            pool_references.extend(
+                DexAssetPairing::new(offer_asset, ask_asset.clone(), &dca.dex)
+                    .resolve(&deps.querier, ans_host)?,
+            );
+            offer_asset = ask_asset;
+        }
+    }
+    Ok(pool_references)
+}
+
+/// Get dca, ended dcas are reported as completed
+fn query_dca(deps: Deps, env: &Env, app: &DCAApp, dca_id: String) -> AppResult<DCAResponse> {
+    let dca = DCA_LIST
+        .may_load(deps.storage, dca_id.clone())?
+        .map(|mut dca| {
+            dca.status = dca.status_at(&env.block);
+            dca
+        });
     let ans_host = app.ans_host(deps)?;
     let pool_references = if let Some(entry) = dca.as_ref() {
-        DexAssetPairing::new(
-            entry.source_asset.name.clone(),
-            entry.target_asset.clone(),
-            &entry.dex,
-        )
-        .resolve(&deps.querier, &ans_host)?
+        dca_pool_references(deps, &ans_host, entry)?
     } else {
         vec![]
     };
+    // Only an active dca has a task
+    let gas_limit = match &dca {
+        Some(entry) if entry.status == DCAStatus::Active => {
+            TASK_GAS_LIMITS.may_load(deps.storage, dca_id)?
+        }
+        _ => None,
+    };
     Ok(DCAResponse {
         dca,
         pool_references,
+        gas_limit,
+    })
+}
+
+/// List dcas, ordered by dca id
+fn query_dca_list(
+    deps: Deps,
+    env: &Env,
+    app: &DCAApp,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    with_pool_references: bool,
+) -> AppResult<DCAListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let entries = DCA_LIST
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let ans_host = app.ans_host(deps)?;
+    let dcas = entries
+        .into_iter()
+        .map(|(dca_id, mut dca)| {
+            dca.status = dca.status_at(&env.block);
+            let pool_references = if with_pool_references {
+                Some(dca_pool_references(deps, &ans_host, &dca)?)
+            } else {
+                None
+            };
+            Ok(DCAListItem {
+                dca_id,
+                dca,
+                pool_references,
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    Ok(DCAListResponse { dcas })
+}
+
+/// List executions of the dca
+fn query_dca_history(
+    deps: Deps,
+    dca_id: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<DCAHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let executions = DCA_HISTORY
+        .prefix(dca_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(DCAHistoryResponse { executions })
+}
+
+/// Get running totals of the dca
+fn query_dca_stats(deps: Deps, dca_id: String) -> StdResult<DCAStatsResponse> {
+    let stats = DCA_STATS
+        .may_load(deps.storage, dca_id)?
+        .unwrap_or_default();
+    Ok(DCAStatsResponse { stats })
+}
+
+/// Simulate the hops of the leg on the dex
+fn simulate_leg(
+    deps: Deps,
+    app: &DCAApp,
+    dex: &DexName,
+    leg: ConvertLeg,
+) -> AppResult<SimulatedLeg> {
+    let dex = app.dex(deps, dex.clone());
+    let mut offer_asset = leg.offered.clone();
+    let mut spreads = vec![];
+    for ask_asset in leg.hops {
+        let simulation = dex.simulate_swap(offer_asset, ask_asset.clone())?;
+        spreads.push(OfferAsset::new(ask_asset.clone(), simulation.spread_amount));
+        offer_asset = OfferAsset::new(ask_asset, simulation.return_amount);
+    }
+    Ok(SimulatedLeg {
+        offer_asset: leg.offered,
+        ask_asset: offer_asset.name,
+        return_amount: offer_asset.amount,
+        spreads,
+        min_return: leg.min_return,
+    })
+}
+
+/// Preview the next execution of the dca, the same way convert sizes it
+fn query_simulate_convert(
+    deps: Deps,
+    env: Env,
+    app: &DCAApp,
+    dca_id: String,
+) -> AppResult<SimulateConvertResponse> {
+    let mut response = SimulateConvertResponse {
+        refill: false,
+        completes: false,
+        skipped: None,
+        price: None,
+        dex: None,
+        legs: vec![],
+        capped_by: vec![],
+    };
+    // Convert skips during the halt, without refilling the task
+    if HALTED.may_load(deps.storage)?.unwrap_or_default() {
+        response.skipped = Some("halted".to_owned());
+        return Ok(response);
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let dca = DCA_LIST.load(deps.storage, dca_id.clone())?;
+    if dca.status != DCAStatus::Active {
+        return Err(AppError::DCANotActive { dca_id });
+    }
+
+    let cron_cat = app.cron_cat(deps);
+    let refill = needs_refill(&cron_cat, &env, &dca_id, &config)?;
+
+    let mut stats = DCA_STATS
+        .may_load(deps.storage, dca_id)?
+        .unwrap_or_default();
+    // A completed dca has no task left to refill
+    let final_run = dca.is_final_run(&env.block);
+    match plan_convert(deps, &env, app, &config, dca, &mut stats)? {
+        ConvertPlan::Complete => response.completes = true,
+        ConvertPlan::Skip { reason, price } => {
+            response.skipped = Some(reason.to_owned());
+            response.price = price;
+            response.completes = final_run;
+            response.refill = refill && !final_run;
+        }
+        ConvertPlan::Swap {
+            dex,
+            legs,
+            price,
+            capped_by,
+            ..
+        } => {
+            response.legs = legs
+                .into_iter()
+                .map(|leg| simulate_leg(deps, app, &dex, leg))
+                .collect::<AppResult<_>>()?;
+            response.price = price;
+            response.dex = Some(dex);
+            response.capped_by = capped_by.into_iter().map(ToOwned::to_owned).collect();
+            response.refill = refill && !final_run;
+        }
+    }
+    Ok(response)
+}
+
+/// Number of executions the funds pay for, unknown if the cost isn't
+fn funded_executions(funds: Uint128, cost: Uint128) -> Option<u64> {
+    funds
+        .checked_div(cost)
+        .ok()
+        .map(|executions| u64::try_from(executions.u128()).unwrap_or(u64::MAX))
+}
+
+/// Check the funding of the task and the source asset, and whether the next run would fail
+fn query_dca_health(
+    deps: Deps,
+    env: Env,
+    app: &DCAApp,
+    dca_id: String,
+) -> AppResult<DCAHealthResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let dca = DCA_LIST.load(deps.storage, dca_id.clone())?;
+    let cron_cat = app.cron_cat(deps);
+
+    let task = cron_cat
+        .query_task_information(env.contract.address.clone(), dca_id.clone())?
+        .task;
+    let task_balance = cron_cat
+        .query_task_balance(env.contract.address.clone(), dca_id.clone())?
+        .balance
+        .map_or(Uint128::zero(), |balance| balance.native_balance);
+    let execution_cost = match &task {
+        Some(task) => {
+            let amount = &task.amount_for_one_task;
+            let fee = u64::from(amount.agent_fee) + u64::from(amount.treasury_fee);
+            let gas = amount.gas * (10_000 + fee) / 10_000;
+            let gas_cost = amount
+                .gas_price
+                .calculate(gas)
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+            Uint128::from(gas_cost)
+        }
+        None => Uint128::zero(),
+    };
+
+    // Size the next swap the same way convert does, the runway is unknown if it can't be sized
+    let source_balance = app.bank(deps).balance(&dca.source_asset.name)?.amount;
+    let mut stats = DCA_STATS
+        .may_load(deps.storage, dca_id)?
+        .unwrap_or_default();
+    let plan = plan_convert(deps, &env, app, &config, dca.clone(), &mut stats);
+    let offer_amount = match &plan {
+        Ok(ConvertPlan::Swap { legs, .. }) => legs.iter().map(|leg| leg.offered.amount).sum(),
+        _ => Uint128::zero(),
+    };
+    let source_funded_executions = funded_executions(source_balance, offer_amount);
+
+    // Convert refills the task from the proxy, whenever it drops below the threshold
+    // A native source asset is spent from the same balance, the next swap is set aside
+    let proxy_native_balance = deps
+        .querier
+        .query_balance(app.proxy_address(deps)?, &config.native_denom)?
+        .amount;
+    let refill_funds = if native_denom(deps, app, &dca.source_asset.name)?.as_ref()
+        == Some(&config.native_denom)
+    {
+        proxy_native_balance.saturating_sub(offer_amount)
+    } else {
+        proxy_native_balance
+    };
+    let refills = funded_executions(refill_funds, config.dca_creation_amount).unwrap_or(0);
+    let refill_amount = config
+        .dca_creation_amount
+        .checked_mul(refills.into())
+        .unwrap_or(Uint128::MAX);
+    let gas_funded_executions =
+        funded_executions(task_balance.saturating_add(refill_amount), execution_cost);
+
+    // A halted convert is skipped without failing, the halt is reported by the config
+    let failure = if dca.status_at(&env.block) != DCAStatus::Active {
+        Some("not_active".to_owned())
+    } else if task.is_none() {
+        Some("task_missing".to_owned())
+    } else if task_balance < execution_cost {
+        Some("task_balance_too_low".to_owned())
+    } else if task_balance < config.refill_threshold && refill_funds < config.dca_creation_amount {
+        Some("refill_not_funded".to_owned())
+    } else {
+        match plan {
+            Err(err) => Some(err.to_string()),
+            Ok(ConvertPlan::Swap { .. }) if source_balance < offer_amount => {
+                Some("source_not_funded".to_owned())
+            }
+            Ok(_) => None,
+        }
+    };
+
+    Ok(DCAHealthResponse {
+        task_exists: task.is_some(),
+        task_balance,
+        refill_threshold: config.refill_threshold,
+        execution_cost,
+        source_balance,
+        gas_funded_executions,
+        source_funded_executions,
+        funded_executions: gas_funded_executions
+            .zip(source_funded_executions)
+            .map(|(gas, source)| gas.min(source)),
+        failure,
     })
 }
@@ -1,12 +1,15 @@
 use abstract_core::objects::{AssetEntry, DexName, PoolReference};
 use abstract_dex_adapter::msg::OfferAsset;
 use cosmwasm_schema::QueryResponses;
-use cosmwasm_std::{Decimal, Uint128};
-use croncat_app::croncat_integration_utils::CronCatInterval;
+use cosmwasm_std::{wasm_execute, CosmosMsg, Decimal, StdResult, Timestamp, Uint128};
+use croncat_app::croncat_integration_utils::{
+    CronCatBoundary, CronCatBoundaryHeight, CronCatBoundaryTime, CronCatInterval,
+};
 
 use crate::{
-    contract::DCAApp,
-    state::{Config, DCAEntry},
+    contract::{AppResult, DCAApp},
+    error::AppError,
+    state::{Config, DCAEntry, DCAExecution, DCAStats},
 };
 
 // This is used for type safety
@@ -38,6 +41,165 @@ impl Frequency {
         }
     }
 }
+
+#[cosmwasm_schema::cw_serde]
+pub enum DCABoundary {
+    /// Boundary at the given block height.
+    Height(u64),
+    /// Boundary at the given block time.
+    Time(Timestamp),
+}
+
+impl DCABoundary {
+    /// Combine start and end into a croncat boundary.
+    /// Both have to be of the same kind and start has to be before end.
+    pub fn to_boundary(
+        start: Option<Self>,
+        end: Option<Self>,
+    ) -> AppResult<Option<CronCatBoundary>> {
+        let height = |start: Option<u64>, end: Option<u64>| {
+            CronCatBoundary::Height(CronCatBoundaryHeight {
+                start: start.map(Into::into),
+                end: end.map(Into::into),
+            })
+        };
+        let time = |start, end| CronCatBoundary::Time(CronCatBoundaryTime { start, end });
+
+        let boundary = match (start, end) {
+            (None, None) => None,
+            (Some(Self::Height(start)), None) => Some(height(Some(start), None)),
+            (None, Some(Self::Height(end))) => Some(height(None, Some(end))),
+            (Some(Self::Height(start)), Some(Self::Height(end))) if start < end => {
+                Some(height(Some(start), Some(end)))
+            }
+            (Some(Self::Time(start)), None) => Some(time(Some(start), None)),
+            (None, Some(Self::Time(end))) => Some(time(None, Some(end))),
+            (Some(Self::Time(start)), Some(Self::Time(end))) if start < end => {
+                Some(time(Some(start), Some(end)))
+            }
+            _ => return Err(AppError::InvalidBoundaries {}),
+        };
+        Ok(boundary)
+    }
+}
+
+#[cosmwasm_schema::cw_serde]
+#[derive(Default)]
+pub enum DCAStrategy {
+    /// Swap the source asset amount every period.
+    #[default]
+    Fixed,
+    /// Grow the value of the target asset holdings by `value_increment` of the source asset
+    /// every period, buying only the shortfall.
+    /// The source asset amount caps the purchase of a single period.
+    ValueAveraging { value_increment: Uint128 },
+    /// Swap a percentage of the proxy balance of the source asset every period,
+    /// bounded by optional absolute amounts.
+    /// The source asset amount is ignored.
+    BalancePercentage {
+        percentage: Decimal,
+        min_amount: Option<Uint128>,
+        max_amount: Option<Uint128>,
+    },
+    /// Receive a fixed amount of the target asset every period.
+    /// The offer is simulated and raised by `buffer` to absorb price movement.
+    /// The source asset amount is ignored.
+    ExactOutput { amount: Uint128, buffer: Decimal },
+}
+
+#[cosmwasm_schema::cw_serde]
+#[derive(Default)]
+pub enum FailurePolicy {
+    /// Fail the DCA on the first failed execution.
+    #[default]
+    Stop,
+    /// Keep the task running after failed executions.
+    Skip,
+    /// Keep the task running, fail the DCA after `failures` consecutive failed executions.
+    PauseAfter { failures: u32 },
+}
+
+/// Action run with the purchased asset after the swap
+/// Only native delegation is supported, staking through an adapter is out of scope
+#[cosmwasm_schema::cw_serde]
+pub enum PostSwapAction {
+    /// Delegate the received native tokens to the validator.
+    Delegate { validator: String },
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DipTier {
+    /// Drop of the price since the last execution, e.g. `0.1` for 10%.
+    pub drop: Decimal,
+    /// Multiplier of the purchase amount once the price dropped by `drop`.
+    pub multiplier: Decimal,
+}
+
+/// Optional settings of a DCA, settings left out are off
+#[cosmwasm_schema::cw_serde]
+#[derive(Default)]
+#[serde(default)]
+pub struct DCAOptions {
+    /// Total amount of the source asset to spend
+    pub total_budget: Option<Uint128>,
+    /// Number of executions after which the DCA completes
+    pub max_executions: Option<u64>,
+    /// Height or time of the first purchase
+    pub start: Option<DCABoundary>,
+    /// Height or time after which the DCA completes
+    pub end: Option<DCABoundary>,
+    /// Max spread of the swap, overrides the config value
+    pub max_spread: Option<Decimal>,
+    /// Intermediate assets to swap through, in order
+    pub route: Option<Vec<AssetEntry>>,
+    /// Price in source asset above which the purchase is skipped
+    pub max_price: Option<Decimal>,
+    /// Price in source asset below which the purchase is skipped
+    pub min_price: Option<Decimal>,
+    /// Strategy sizing the purchases, fixed by default
+    pub strategy: Option<DCAStrategy>,
+    /// Tiers multiplying the purchase when the price dropped
+    pub dip_tiers: Option<Vec<DipTier>>,
+    /// Amount of the target asset held by the account, after which the DCA completes
+    pub target_holding: Option<Uint128>,
+    /// Address to send the purchased asset to, instead of the account
+    pub recipient: Option<String>,
+    /// Action run with the purchased asset after the swap
+    pub post_swap: Option<PostSwapAction>,
+    /// Other DEXes to swap on when they return more
+    pub candidate_dexes: Option<Vec<DexName>>,
+    /// DEXes to try, in order, when a swap fails
+    pub fallback_dexes: Option<Vec<DexName>>,
+    /// Handling of failed swaps, stops the DCA by default
+    pub failure_policy: Option<FailurePolicy>,
+    /// Gas limit of the convert task, estimated from the DCA by default
+    pub gas_limit: Option<u64>,
+}
+
+/// Settings of a DCA that can be cleared on update
+#[cosmwasm_schema::cw_serde]
+pub enum DCAOptionKind {
+    TotalBudget,
+    MaxExecutions,
+    Start,
+    End,
+    MaxSpread,
+    Route,
+    MaxPrice,
+    MinPrice,
+    /// Resets the strategy to fixed
+    Strategy,
+    DipTiers,
+    TargetHolding,
+    Recipient,
+    PostSwap,
+    CandidateDexes,
+    FallbackDexes,
+    /// Resets the failure policy to stop
+    FailurePolicy,
+    GasLimit,
+}
+
 /// App instantiate message
 #[cosmwasm_schema::cw_serde]
 pub struct AppInstantiateMsg {
@@ -68,6 +230,23 @@ pub enum DCAExecuteMsg {
         frequency: Frequency,
         /// The DEX to be used for the swap
         dex: DexName,
+        /// Optional settings of the DCA
+        #[serde(default)]
+        options: DCAOptions,
+    },
+    /// Used to create a new DCA splitting the source asset across several assets
+    CreateBasketDCA {
+        /// The name of the asset to be used for purchasing
+        source_asset: OfferAsset,
+        /// The names of the assets to be purchased, with weights adding up to 1
+        targets: Vec<(AssetEntry, Decimal)>,
+        /// The frequency of purchase
+        frequency: Frequency,
+        /// The DEX to be used for the swaps
+        dex: DexName,
+        /// Optional settings of the DCA, some are not supported for baskets
+        #[serde(default)]
+        options: DCAOptions,
     },
     // MultipleCreateDcas
     /// Used to update an existing DCA
@@ -78,20 +257,70 @@ pub enum DCAExecuteMsg {
         new_source_asset: Option<OfferAsset>,
         /// Optional new name of the asset to be purchased
         new_target_asset: Option<AssetEntry>,
+        /// Optional new assets to be purchased by a basket DCA, with weights adding up to 1
+        new_basket: Option<Vec<(AssetEntry, Decimal)>>,
         /// Optional new frequency of purchase
         new_frequency: Option<Frequency>,
         /// Optional new DEX to be used for the swap
         new_dex: Option<DexName>,
+        /// Settings to change, settings left out are kept
+        #[serde(default)]
+        new_options: DCAOptions,
+        /// Settings to turn off, cleared before the new settings are applied
+        #[serde(default)]
+        clear_options: Vec<DCAOptionKind>,
     },
 
-    /// Used to cancel an existing DCA
+    /// Used to cancel an existing DCA, removing its stats and history
     CancelDCA {
         /// Unique identifier for the DCA
         dca_id: String,
     },
+    /// Used to pause an existing DCA, keeping its id and stats
+    PauseDCA {
+        /// Unique identifier for the DCA
+        dca_id: String,
+    },
+    /// Used to resume a paused DCA
+    ResumeDCA {
+        /// Unique identifier for the DCA
+        dca_id: String,
+    },
+    /// Used to stop every DCA of the account at once
+    /// Converts of tasks kept during the halt are skipped rather than rejected,
+    /// a rejected convert would count as a failed execution of the task
+    HaltAll {
+        /// Remove tasks of all active DCAs, pausing them
+        remove_tasks: bool,
+    },
+    /// Used to lift the halt
+    UnhaltAll {},
+    /// Used to register a contract notified after every DCA execution
+    AddHook {
+        addr: String,
+    },
+    /// Used to unregister a hook contract
+    RemoveHook {
+        addr: String,
+    },
     Convert {
         dca_id: String,
     },
+    /// Internal, swap and assert the swap returned at least `min_return`
+    /// A short return reverts the swap, so it's retried on the fallback dexes
+    SwapWithMinReturn {
+        dex: DexName,
+        offer_asset: OfferAsset,
+        ask_asset: AssetEntry,
+        max_spread: Decimal,
+        min_return: Uint128,
+    },
+    /// Internal, assert the proxy received at least `min_return` of the asset
+    AssertMinReturn {
+        asset: AssetEntry,
+        balance_before: Uint128,
+        min_return: Uint128,
+    },
 }
 
 #[cosmwasm_schema::cw_serde]
@@ -103,18 +332,155 @@ pub enum DCAQueryMsg {
     Config {},
     #[returns(DCAResponse)]
     DCA { dca_id: String },
+    /// List all DCAs of the account, ordered by their id as a string, so `dca_10` comes before `dca_2`
+    #[returns(DCAListResponse)]
+    ListDCAs {
+        /// Id of the DCA to start listing after
+        start_after: Option<String>,
+        /// Maximum number of DCAs to return
+        limit: Option<u32>,
+        /// Resolve pool references for every listed DCA
+        with_pool_references: Option<bool>,
+    },
+    /// Executed swaps of the dca, oldest first
+    #[returns(DCAHistoryResponse)]
+    DCAHistory {
+        dca_id: String,
+        /// Execution number to start listing after
+        start_after: Option<u64>,
+        /// Maximum number of executions to return
+        limit: Option<u32>,
+    },
+    /// Running totals and average price of the dca
+    #[returns(DCAStatsResponse)]
+    DCAStats { dca_id: String },
+    /// Contracts notified after every dca execution
+    #[returns(cw_controllers::HooksResponse)]
+    Hooks {},
+    /// Preview of what converting the dca would do right now, without changing any state
+    #[returns(SimulateConvertResponse)]
+    SimulateConvert { dca_id: String },
+    /// Funding of the croncat task and the source asset of the dca
+    #[returns(DCAHealthResponse)]
+    DCAHealth { dca_id: String },
 }
 
 #[cosmwasm_schema::cw_serde]
 pub enum AppMigrateMsg {}
 
+/// Sent to the hook contracts after every executed swap of a dca
+#[cosmwasm_schema::cw_serde]
+pub struct DCAExecutedHook {
+    pub dca_id: String,
+    pub offered: OfferAsset,
+    pub received: OfferAsset,
+    pub height: u64,
+}
+
+impl DCAExecutedHook {
+    /// Wrap the hook into an execute message for the hook contract
+    pub fn into_cosmos_msg(self, contract_addr: String) -> StdResult<CosmosMsg> {
+        let msg = DCAHookMsg::DCAExecuted(self);
+        Ok(wasm_execute(contract_addr, &msg, vec![])?.into())
+    }
+}
+
+/// Execute message the hook contracts have to handle
+#[cosmwasm_schema::cw_serde]
+pub enum DCAHookMsg {
+    DCAExecuted(DCAExecutedHook),
+}
+
 #[cosmwasm_schema::cw_serde]
 pub struct ConfigResponse {
     pub config: Config,
+    /// Whether all dcas are halted
+    pub halted: bool,
 }
 
 #[cosmwasm_schema::cw_serde]
 pub struct DCAResponse {
     pub dca: Option<DCAEntry>,
+    /// Pool references of every hop of every leg, in order
     pub pool_references: Vec<PoolReference>,
+    /// Gas limit the convert task was created with, none without a task
+    pub gas_limit: Option<u64>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DCAListItem {
+    pub dca_id: String,
+    pub dca: DCAEntry,
+    pub pool_references: Option<Vec<PoolReference>>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DCAListResponse {
+    pub dcas: Vec<DCAListItem>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DCAHistoryResponse {
+    /// Executions with their execution number
+    pub executions: Vec<(u64, DCAExecution)>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DCAStatsResponse {
+    pub stats: DCAStats,
+}
+
+/// Simulated swap of a share of the source asset into one target asset
+#[cosmwasm_schema::cw_serde]
+pub struct SimulatedLeg {
+    pub offer_asset: OfferAsset,
+    pub ask_asset: AssetEntry,
+    /// Amount of the ask asset the dex simulation returns over the whole route
+    pub return_amount: Uint128,
+    /// Spread of every hop of the route, in the ask asset of the hop
+    pub spreads: Vec<OfferAsset>,
+    /// Minimum amount of the ask asset the swap has to return
+    pub min_return: Option<Uint128>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct SimulateConvertResponse {
+    /// Whether the task balance would be refilled
+    pub refill: bool,
+    /// Whether the dca would be completed instead of swapping
+    pub completes: bool,
+    /// Reason the swap would be skipped
+    pub skipped: Option<String>,
+    /// Price of the target asset, if the price guard simulated the route
+    pub price: Option<Decimal>,
+    /// Dex the swap would run on
+    pub dex: Option<DexName>,
+    /// Legs of the swap, empty if there is no swap
+    pub legs: Vec<SimulatedLeg>,
+    /// Budget caps that shortened the swap
+    pub capped_by: Vec<String>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DCAHealthResponse {
+    /// Whether the croncat task of the dca exists
+    pub task_exists: bool,
+    /// Native balance of the task
+    pub task_balance: Uint128,
+    /// Task balance below which convert refills the task
+    pub refill_threshold: Uint128,
+    /// Native tokens the task is charged for one execution, including croncat fees
+    pub execution_cost: Uint128,
+    /// Balance of the source asset on the account proxy
+    pub source_balance: Uint128,
+    /// Executions the task balance pays for, with the refills the proxy can afford besides the next swap
+    /// Unknown without a task
+    pub gas_funded_executions: Option<u64>,
+    /// Executions the source balance pays for, at the amount of the next swap
+    /// Unknown if the next run doesn't swap
+    pub source_funded_executions: Option<u64>,
+    /// Executions funded by both the task balance and the source balance
+    pub funded_executions: Option<u64>,
+    /// Reason the next run of the dca will fail, if it will
+    pub failure: Option<String>,
 }
@@ -0,0 +1,13 @@
+use crate::contract::{AppResult, DCAApp};
+
+use abstract_sdk::features::AbstractResponse;
+use cosmwasm_std::{DepsMut, Env, Reply, Response};
+
+/// Failed hooks are only reported, the dca execution goes through
+pub fn hook_reply(_deps: DepsMut, _env: Env, app: DCAApp, reply: Reply) -> AppResult {
+    let error = reply.result.into_result().err().unwrap_or_default();
+    Ok(app.tag_response(
+        Response::new().add_attribute("hook_error", error),
+        "hook_reply",
+    ))
+}
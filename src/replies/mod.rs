@@ -1,5 +1,11 @@
+mod hook;
 mod instantiate;
+mod swap;
 
+pub use hook::hook_reply;
 pub use instantiate::instantiate_reply;
+pub use swap::swap_reply;
 
 pub const INSTANTIATE_REPLY_ID: u64 = 1u64;
+pub const SWAP_REPLY_ID: u64 = 2u64;
+pub const HOOK_REPLY_ID: u64 = 3u64;
@@ -0,0 +1,239 @@
+use crate::contract::{AppResult, DCAApp};
+use crate::error::AppError;
+use crate::handlers::execute::{
+    apply_failure_policy, complete_dca, holding_shortfall, native_denom, refill_task_msg,
+    swap_next_hop,
+};
+use crate::msg::{DCAExecutedHook, PostSwapAction};
+use crate::replies::HOOK_REPLY_ID;
+use crate::state::{
+    DCAEntry, DCAExecution, DCAStats, DCATarget, ExecutedLeg, PendingConvert, CONFIG, DCA_HISTORY,
+    DCA_LIST, DCA_STATS, HOOKS, PENDING_CONVERT,
+};
+
+use abstract_dex_adapter::msg::OfferAsset;
+use abstract_sdk::features::AbstractResponse;
+use abstract_sdk::{Execution, TransferInterface};
+use cosmwasm_std::{
+    coin, Decimal, DepsMut, Env, Order, Reply, Response, StakingMsg, StdError, SubMsg,
+};
+use croncat_app::CronCatInterface;
+
+/// Continue along the route and legs of the dca, record the execution once every leg is done
+/// A failed swap, or one that returned less than the min return, is retried on the next fallback dex
+pub fn swap_reply(mut deps: DepsMut, env: Env, app: DCAApp, reply: Reply) -> AppResult {
+    let mut pending = PENDING_CONVERT.load(deps.storage)?;
+
+    if let Err(error) = reply.result.into_result() {
+        if pending.fallback_dexes.is_empty() {
+            return swap_failed(deps, env, app, pending, error);
+        }
+        // The rest of the execution runs on the fallback dex
+        pending.dex = pending.fallback_dexes.remove(0);
+        let dca_id = pending.dca_id.clone();
+        let fallback_dex = pending.dex.clone();
+        let offer_asset = pending.offer_asset.clone();
+        let swap_msg = swap_next_hop(deps, &env, &app, pending, offer_asset)?;
+        return Ok(app.tag_response(
+            Response::new()
+                .add_submessage(swap_msg)
+                .add_attribute("dca_id", dca_id)
+                .add_attribute("fallback_dex", fallback_dex),
+            "swap_reply",
+        ));
+    }
+
+    // Measure the received amount by the balance change of the proxy
+    let ask_asset = pending.leg.hops.remove(0);
+    let ask_balance = app.bank(deps.as_ref()).balance(&ask_asset)?.amount;
+    let received = ask_balance
+        .checked_sub(pending.ask_balance_before)
+        .map_err(StdError::from)?;
+    if received.is_zero() {
+        return swap_failed(
+            deps,
+            env,
+            app,
+            pending,
+            AppError::NothingReceived {}.to_string(),
+        );
+    }
+    let received = OfferAsset::new(ask_asset, received);
+
+    // Swap the received amount into the next hop
+    if !pending.leg.hops.is_empty() {
+        let dca_id = pending.dca_id.clone();
+        let hop_received = received.amount;
+        let swap_msg = swap_next_hop(deps, &env, &app, pending, received)?;
+        return Ok(app.tag_response(
+            Response::new()
+                .add_submessage(swap_msg)
+                .add_attribute("dca_id", dca_id)
+                .add_attribute("hop_received", hop_received),
+            "swap_reply",
+        ));
+    }
+
+    // Min return of the route was asserted within the last swap
+    pending.swaps.push((pending.leg.offered.clone(), received));
+
+    // Swap the next leg of the basket
+    if !pending.next_legs.is_empty() {
+        pending.leg = pending.next_legs.remove(0);
+        let dca_id = pending.dca_id.clone();
+        let offer_asset = pending.leg.offered.clone();
+        let swap_msg = swap_next_hop(deps, &env, &app, pending, offer_asset)?;
+        return Ok(app.tag_response(
+            Response::new()
+                .add_submessage(swap_msg)
+                .add_attribute("dca_id", dca_id),
+            "swap_reply",
+        ));
+    }
+    PENDING_CONVERT.remove(deps.storage);
+
+    let dca = DCA_LIST.load(deps.storage, pending.dca_id.clone())?;
+    let (stats, mut response) = record_swaps(deps.branch(), &env, &app, &pending, &dca, false)?;
+
+    // Stop the dca once it used up its budget or executions, reached its end or target holding
+    if dca.is_exhausted(&stats)
+        || dca.is_final_run(&env.block)
+        || holding_shortfall(deps.as_ref(), &app, &dca)?
+            .map_or(false, |shortfall| shortfall.is_zero())
+    {
+        response = response
+            .add_message(complete_dca(deps, &app, pending.dca_id, dca)?)
+            .add_attribute("completed", "true");
+    } else if pending.refill {
+        let config = CONFIG.load(deps.storage)?;
+        let cron_cat = app.cron_cat(deps.as_ref());
+        response = response.add_message(refill_task_msg(&cron_cat, pending.dca_id, &config)?);
+    }
+
+    Ok(app.tag_response(response, "swap_reply"))
+}
+
+/// Handle a swap that failed on every dex by the failure policy of the dca
+fn swap_failed(
+    mut deps: DepsMut,
+    env: Env,
+    app: DCAApp,
+    pending: PendingConvert,
+    error: String,
+) -> AppResult {
+    let dca_id = pending.dca_id.clone();
+    let dca = DCA_LIST.load(deps.storage, dca_id.clone())?;
+    PENDING_CONVERT.remove(deps.storage);
+
+    // Legs finished before the failure are kept
+    let (_, response) = record_swaps(deps.branch(), &env, &app, &pending, &dca, true)?;
+    let response = response.add_attribute("swap_error", error);
+    let response = apply_failure_policy(deps, &env, &app, dca_id, dca, pending.refill, response)?;
+    Ok(app.tag_response(response, "swap_reply"))
+}
+
+/// Record the finished swaps of the execution, forward or delegate what they returned and notify the hooks
+/// A failed execution keeps the swaps of the legs finished before the failure
+fn record_swaps(
+    deps: DepsMut,
+    env: &Env,
+    app: &DCAApp,
+    pending: &PendingConvert,
+    dca: &DCAEntry,
+    failed: bool,
+) -> AppResult<(DCAStats, Response)> {
+    let stats = DCA_STATS.update(deps.storage, pending.dca_id.clone(), |stats| {
+        let mut stats = stats.unwrap_or_default();
+        if !pending.swaps.is_empty() {
+            match &dca.target {
+                DCATarget::Asset(_) => {
+                    let (offered, received) = &pending.swaps[0];
+                    stats.record(offered.amount, received.amount)?;
+                }
+                DCATarget::Basket(_) => stats.record_basket(&pending.swaps)?,
+            }
+        }
+        if failed {
+            stats.consecutive_failures += 1;
+        } else {
+            stats.consecutive_failures = 0;
+        }
+        AppResult::Ok(stats)
+    })?;
+    let mut response = Response::new().add_attribute("dca_id", pending.dca_id.clone());
+    if pending.swaps.is_empty() {
+        return Ok((stats, response));
+    }
+
+    // Forward exactly what the swaps returned
+    if let Some(recipient) = &dca.recipient {
+        let received = pending
+            .swaps
+            .iter()
+            .map(|(_, received)| received.clone())
+            .collect::<Vec<_>>();
+        response = response
+            .add_message(app.bank(deps.as_ref()).transfer(received, recipient)?)
+            .add_attribute("recipient", recipient);
+    }
+
+    // Delegate the received tokens in the same transaction
+    if let Some(PostSwapAction::Delegate { validator }) = &dca.post_swap {
+        let (_, received) = &pending.swaps[0];
+        let denom = native_denom(deps.as_ref(), app, &received.name)?
+            .ok_or(AppError::InvalidPostSwapAction {})?;
+        let delegate_msg = app
+            .executor(deps.as_ref())
+            .execute(vec![StakingMsg::Delegate {
+                validator: validator.clone(),
+                amount: coin(received.amount.u128(), denom),
+            }
+            .into()])?;
+        response = response
+            .add_message(delegate_msg)
+            .add_attribute("delegated", received.amount);
+    }
+
+    let mut legs = vec![];
+    for (offered, received) in pending.swaps.iter().cloned() {
+        // Notify the hooks, a failing hook doesn't revert the swap
+        let hook = DCAExecutedHook {
+            dca_id: pending.dca_id.clone(),
+            offered: offered.clone(),
+            received: received.clone(),
+            height: env.block.height,
+        };
+        response = response.add_submessages(HOOKS.prepare_hooks(deps.storage, |addr| {
+            let hook_msg = hook.clone().into_cosmos_msg(addr.to_string())?;
+            Ok(SubMsg::reply_on_error(hook_msg, HOOK_REPLY_ID))
+        })?);
+
+        response = response.add_attribute("received", received.amount);
+        legs.push(ExecutedLeg {
+            price: Decimal::from_ratio(offered.amount, received.amount),
+            offered,
+            received,
+        });
+    }
+
+    // Every leg of the run is recorded in one execution
+    let execution_id = DCA_HISTORY
+        .prefix(pending.dca_id.clone())
+        .keys(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map_or(0, |id| id + 1);
+    let execution = DCAExecution {
+        height: env.block.height,
+        time: env.block.time,
+        legs,
+        dex: pending.dex.clone(),
+    };
+    DCA_HISTORY.save(
+        deps.storage,
+        (pending.dca_id.clone(), execution_id),
+        &execution,
+    )?;
+
+    Ok((stats, response))
+}
@@ -1,9 +1,23 @@
 use abstract_core::objects::{AssetEntry, DexName};
 use abstract_dex_adapter::msg::OfferAsset;
-use cosmwasm_std::{Decimal, Uint128};
+use cosmwasm_std::{Addr, BlockInfo, Decimal, StdResult, Timestamp, Uint128};
+use cw_controllers::Hooks;
 use cw_storage_plus::{Item, Map};
 
-use crate::msg::Frequency;
+use crate::msg::{DCABoundary, DCAStrategy, DipTier, FailurePolicy, Frequency, PostSwapAction};
+
+/// Gas of the convert itself, including a task refill
+const CONVERT_GAS: u64 = 80_000;
+/// Gas of a single swap and its reply
+const SWAP_GAS: u64 = 170_000;
+/// Gas of a simulation done before the swap
+const SIMULATION_GAS: u64 = 40_000;
+/// Gas of forwarding or delegating the purchase
+const POST_SWAP_GAS: u64 = 60_000;
+/// Gas of notifying a hook of an executed swap
+const HOOK_GAS: u64 = 50_000;
+/// Gas limit the tasks were created with before the estimate, kept as its floor
+const MIN_TASK_GAS: u64 = 300_000;
 
 #[cosmwasm_schema::cw_serde]
 pub struct Config {
@@ -13,14 +27,381 @@ pub struct Config {
     pub max_spread: Decimal,
 }
 
+#[cosmwasm_schema::cw_serde]
+pub enum DCAStatus {
+    /// The dca is scheduled and converting
+    Active,
+    /// The dca has no task and waits to be resumed
+    Paused,
+    /// The dca reached its budget, execution limit, end or target holding
+    Completed,
+    /// The dca reached its limit of consecutive failed swaps and has no task
+    Failed,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub enum DCATarget {
+    /// The whole source amount is swapped into one asset
+    Asset(AssetEntry),
+    /// The source amount is split across assets by weight, weights add up to 1
+    Basket(Vec<(AssetEntry, Decimal)>),
+}
+
 #[cosmwasm_schema::cw_serde]
 pub struct DCAEntry {
     pub source_asset: OfferAsset,
-    pub target_asset: AssetEntry,
+    pub target: DCATarget,
     pub frequency: Frequency,
     pub dex: DexName,
+    /// Total amount of the source asset the dca is allowed to spend
+    pub total_budget: Option<Uint128>,
+    /// Number of executions after which the dca completes
+    pub max_executions: Option<u64>,
+    /// Height or time of the first purchase
+    pub start: Option<DCABoundary>,
+    /// Height or time after which the dca completes
+    pub end: Option<DCABoundary>,
+    /// Max spread of the swap, overrides the config value
+    pub max_spread: Option<Decimal>,
+    /// Intermediate assets to swap through, in order, before the target asset.
+    /// Only for a single target asset
+    pub route: Option<Vec<AssetEntry>>,
+    /// Price of the target asset in source asset, above which the purchase is skipped
+    pub max_price: Option<Decimal>,
+    /// Price of the target asset in source asset, below which the purchase is skipped
+    pub min_price: Option<Decimal>,
+    /// How much of the source asset is swapped every period
+    pub strategy: DCAStrategy,
+    /// Tiers multiplying the purchase amount by the price drop since the last execution
+    pub dip_tiers: Option<Vec<DipTier>>,
+    /// Amount of the target asset held by the proxy, after which the dca completes
+    pub target_holding: Option<Uint128>,
+    /// Address the purchased assets are sent to, instead of the proxy
+    pub recipient: Option<Addr>,
+    /// Action run with the received asset after the swap
+    pub post_swap: Option<PostSwapAction>,
+    /// Other dexes to simulate the swap on, the one with the best return is used
+    pub candidate_dexes: Option<Vec<DexName>>,
+    /// Dexes to retry a failed swap on, in order
+    pub fallback_dexes: Option<Vec<DexName>>,
+    /// Handling of failed swaps
+    /// Swaps of a failed execution that went through stay on the proxy
+    pub failure_policy: FailurePolicy,
+    /// Gas limit of the convert task, overrides the estimate
+    pub gas_limit: Option<u64>,
+    pub status: DCAStatus,
+}
+
+impl DCAEntry {
+    /// Legs of the dca: share of the source asset and assets to swap into, in order
+    pub fn legs(&self) -> Vec<(Decimal, Vec<AssetEntry>)> {
+        match &self.target {
+            DCATarget::Asset(target_asset) => {
+                let mut hops = self.route.clone().unwrap_or_default();
+                hops.push(target_asset.clone());
+                vec![(Decimal::one(), hops)]
+            }
+            DCATarget::Basket(basket) => basket
+                .iter()
+                .map(|(asset, weight)| (*weight, vec![asset.clone()]))
+                .collect(),
+        }
+    }
+
+    /// Assets to swap through from the target asset back to the source asset
+    pub fn reverse_hops(&self) -> Vec<AssetEntry> {
+        let mut hops = self.route.clone().unwrap_or_default();
+        hops.reverse();
+        hops.push(self.source_asset.name.clone());
+        hops
+    }
+
+    /// Gas limit of the convert task, estimated from the features of the dca unless overridden
+    pub fn task_gas_limit(&self, hooks: usize) -> u64 {
+        if let Some(gas_limit) = self.gas_limit {
+            return gas_limit;
+        }
+        let legs = self.legs();
+        let swaps = legs.iter().map(|(_, hops)| hops.len()).sum::<usize>() as u64;
+
+        // Every fallback dex may retry every swap
+        let attempts = 1 + self.fallback_dexes.as_ref().map_or(0, Vec::len) as u64;
+
+        let mut simulations = self.candidate_dexes.as_ref().map_or(0, Vec::len) as u64;
+        if self.route.is_some() || self.max_price.is_some() || self.min_price.is_some() {
+            simulations += 1;
+        }
+        if self.dip_tiers.is_some() {
+            simulations += 1;
+        }
+        if self.target_holding.is_some() {
+            simulations += 2;
+        }
+        simulations += match self.strategy {
+            DCAStrategy::ValueAveraging { .. } => 1,
+            DCAStrategy::ExactOutput { .. } => 2,
+            _ => 0,
+        };
+        let post_swap_actions = self.recipient.is_some() as u64 + self.post_swap.is_some() as u64;
+
+        let estimate = CONVERT_GAS
+            + SWAP_GAS * swaps * attempts
+            + SIMULATION_GAS * simulations
+            + POST_SWAP_GAS * post_swap_actions
+            + HOOK_GAS * hooks as u64 * legs.len() as u64;
+        estimate.max(MIN_TASK_GAS)
+    }
+
+    /// Split the offer across the legs, the last leg gets the rounding remainder
+    pub fn split_offer(&self, offer: &OfferAsset) -> Vec<(OfferAsset, Vec<AssetEntry>)> {
+        let legs = self.legs();
+        let last = legs.len() - 1;
+        let mut remaining = offer.amount;
+        legs.into_iter()
+            .enumerate()
+            .map(|(index, (weight, hops))| {
+                let amount = if index == last {
+                    remaining
+                } else {
+                    offer.amount * weight
+                };
+                remaining -= amount;
+                (OfferAsset::new(offer.name.clone(), amount), hops)
+            })
+            .collect()
+    }
+
+    /// Source asset left to spend, if the dca has a budget
+    pub fn remaining_budget(&self, stats: &DCAStats) -> Option<Uint128> {
+        self.total_budget
+            .map(|budget| budget.saturating_sub(stats.total_spent))
+    }
+
+    /// Whether the dca used up its budget or executions
+    pub fn is_exhausted(&self, stats: &DCAStats) -> bool {
+        self.remaining_budget(stats)
+            .map_or(false, |remaining| remaining.is_zero())
+            || self
+                .max_executions
+                .map_or(false, |max| stats.executions >= max)
+    }
+
+    /// Reason to skip the purchase at the given price, if it's out of bounds
+    pub fn price_guard(&self, price: Decimal) -> Option<&'static str> {
+        if self.max_price.map_or(false, |max_price| price > max_price) {
+            Some("price_above_max")
+        } else if self.min_price.map_or(false, |min_price| price < min_price) {
+            Some("price_below_min")
+        } else {
+            None
+        }
+    }
+
+    /// Multiplier of the deepest tier reached by the price drop since the last execution
+    pub fn dip_multiplier(&self, last_price: Decimal, price: Decimal) -> Decimal {
+        let Some(tiers) = &self.dip_tiers else {
+            return Decimal::one();
+        };
+        if price >= last_price {
+            return Decimal::one();
+        }
+        let drop = Decimal::one() - price / last_price;
+        tiers
+            .iter()
+            .filter(|tier| tier.drop <= drop)
+            .max_by_key(|tier| tier.drop)
+            .map_or(Decimal::one(), |tier| tier.multiplier)
+    }
+
+    /// Dex with the highest quote, the earlier dex on a tie and the configured dex without quotes
+    pub fn best_dex(&self, quotes: Vec<(DexName, Uint128)>) -> DexName {
+        let mut best: Option<(DexName, Uint128)> = None;
+        for (dex, return_amount) in quotes {
+            if best
+                .as_ref()
+                .map_or(true, |(_, best_return)| return_amount > *best_return)
+            {
+                best = Some((dex, return_amount));
+            }
+        }
+        best.map_or_else(|| self.dex.clone(), |(dex, _)| dex)
+    }
+
+    /// Whether the end of the dca has passed
+    pub fn has_ended(&self, block: &BlockInfo) -> bool {
+        match self.end {
+            Some(DCABoundary::Height(end)) => block.height > end,
+            Some(DCABoundary::Time(end)) => block.time > end,
+            None => false,
+        }
+    }
+
+    /// Status of the dca at the block
+    /// Croncat drops the task after the end, so an active dca past its end is completed
+    pub fn status_at(&self, block: &BlockInfo) -> DCAStatus {
+        if self.status == DCAStatus::Active && self.has_ended(block) {
+            DCAStatus::Completed
+        } else {
+            self.status.clone()
+        }
+    }
+
+    /// Whether no purchase is scheduled before the end of the dca
+    pub fn is_final_run(&self, block: &BlockInfo) -> bool {
+        match (&self.end, &self.frequency) {
+            (Some(DCABoundary::Height(end)), Frequency::EveryNBlocks(blocks)) => {
+                block.height + blocks > *end
+            }
+            (Some(DCABoundary::Height(end)), _) => block.height >= *end,
+            (Some(DCABoundary::Time(end)), _) => block.time >= *end,
+            (None, _) => false,
+        }
+    }
+}
+
+/// Record of a single DCA execution
+#[cosmwasm_schema::cw_serde]
+pub struct DCAExecution {
+    pub height: u64,
+    pub time: Timestamp,
+    /// Swap of every leg, one per target asset
+    pub legs: Vec<ExecutedLeg>,
+    /// Dex the swaps were executed on
+    pub dex: DexName,
+}
+
+/// Executed swap of a share of the source asset into one target asset
+#[cosmwasm_schema::cw_serde]
+pub struct ExecutedLeg {
+    pub offered: OfferAsset,
+    pub received: OfferAsset,
+    /// Price paid for a unit of the target asset, in source asset
+    pub price: Decimal,
+}
+
+/// Running totals of the dca executions
+#[cosmwasm_schema::cw_serde]
+#[derive(Default)]
+pub struct DCAStats {
+    pub executions: u64,
+    /// Total amount of the source asset spent
+    pub total_spent: Uint128,
+    /// Total amount of the target asset acquired, for a single target asset
+    pub total_received: Uint128,
+    /// Average price paid for a unit of the target asset, in source asset,
+    /// for a single target asset
+    pub average_price: Decimal,
+    /// Totals of every asset of a basket
+    pub legs: Vec<LegStats>,
+    /// Periods the value averaging target has grown by
+    pub periods: u64,
+    /// Price of the last execution, in source asset, for a single target asset
+    pub last_price: Option<Decimal>,
+    /// Failed executions since the last successful one
+    pub consecutive_failures: u32,
+}
+
+impl DCAStats {
+    /// Record an execution swapping into a single target asset
+    pub fn record(&mut self, spent: Uint128, received: Uint128) -> StdResult<()> {
+        self.executions += 1;
+        self.total_spent = self.total_spent.checked_add(spent)?;
+        self.total_received = self.total_received.checked_add(received)?;
+        self.average_price = Decimal::from_ratio(self.total_spent, self.total_received);
+        self.last_price = Some(Decimal::from_ratio(spent, received));
+        Ok(())
+    }
+
+    /// Record an execution of a basket, with the offered and received asset of every leg
+    pub fn record_basket(&mut self, swaps: &[(OfferAsset, OfferAsset)]) -> StdResult<()> {
+        self.executions += 1;
+        for (offered, received) in swaps {
+            self.total_spent = self.total_spent.checked_add(offered.amount)?;
+            let leg = match self.legs.iter().position(|leg| leg.asset == received.name) {
+                Some(index) => &mut self.legs[index],
+                None => {
+                    self.legs.push(LegStats::new(received.name.clone()));
+                    self.legs.last_mut().unwrap()
+                }
+            };
+            leg.record(offered.amount, received.amount)?;
+        }
+        Ok(())
+    }
+}
+
+/// Running totals of one asset of a basket
+#[cosmwasm_schema::cw_serde]
+pub struct LegStats {
+    pub asset: AssetEntry,
+    /// Total amount of the source asset spent on this asset
+    pub total_spent: Uint128,
+    /// Total amount of this asset acquired
+    pub total_received: Uint128,
+    /// Average price paid for a unit of this asset, in source asset
+    pub average_price: Decimal,
+}
+
+impl LegStats {
+    pub fn new(asset: AssetEntry) -> Self {
+        Self {
+            asset,
+            total_spent: Uint128::zero(),
+            total_received: Uint128::zero(),
+            average_price: Decimal::zero(),
+        }
+    }
+
+    pub fn record(&mut self, spent: Uint128, received: Uint128) -> StdResult<()> {
+        self.total_spent = self.total_spent.checked_add(spent)?;
+        self.total_received = self.total_received.checked_add(received)?;
+        self.average_price = Decimal::from_ratio(self.total_spent, self.total_received);
+        Ok(())
+    }
+}
+
+/// Swap of a share of the source asset into one target asset
+#[cosmwasm_schema::cw_serde]
+pub struct ConvertLeg {
+    pub offered: OfferAsset,
+    /// Assets left to swap into, in order, starting with the asset of the current swap
+    pub hops: Vec<AssetEntry>,
+    /// Minimum amount of the target asset to receive over the whole route
+    pub min_return: Option<Uint128>,
+}
+
+/// Convert that is waiting for the swap reply
+#[cosmwasm_schema::cw_serde]
+pub struct PendingConvert {
+    pub dca_id: String,
+    pub dex: DexName,
+    pub max_spread: Decimal,
+    /// Leg of the current swap
+    pub leg: ConvertLeg,
+    /// Offer of the current swap, kept to retry it on a fallback dex
+    pub offer_asset: OfferAsset,
+    /// Dexes left to retry a failed swap on, in order
+    pub fallback_dexes: Vec<DexName>,
+    /// Ask asset balance of the proxy before the current swap
+    pub ask_balance_before: Uint128,
+    /// Legs left to swap after the current one
+    pub next_legs: Vec<ConvertLeg>,
+    /// Offered and received asset of the finished legs
+    pub swaps: Vec<(OfferAsset, OfferAsset)>,
+    /// Refill the task once the swaps are done
+    pub refill: bool,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const NEXT_ID: Item<u64> = Item::new("next_id");
+/// Emergency halt of all dcas
+pub const HALTED: Item<bool> = Item::new("halted");
 pub const DCA_LIST: Map<String, DCAEntry> = Map::new("dca_list");
+/// Executions of the dca, keyed by dca id and execution number
+pub const DCA_HISTORY: Map<(String, u64), DCAExecution> = Map::new("dca_history");
+pub const DCA_STATS: Map<String, DCAStats> = Map::new("dca_stats");
+pub const PENDING_CONVERT: Item<PendingConvert> = Item::new("pending_convert");
+/// Gas limit the convert task of the dca was created with
+pub const TASK_GAS_LIMITS: Map<String, u64> = Map::new("task_gas_limits");
+/// Contracts notified after every dca execution
+pub const HOOKS: Hooks = Hooks::new("hooks");
@@ -5,8 +5,12 @@ use abstract_core::objects::{
     AssetEntry, PoolAddress, PoolReference, UncheckedContractEntry, UniquePoolId,
 };
 use abstract_core::{app::BaseInstantiateMsg, objects::gov_type::GovernanceDetails};
-use abstract_dca_app::msg::{DCAResponse, Frequency};
-use abstract_dca_app::state::{Config, DCAEntry};
+use abstract_dca_app::msg::{
+    DCABoundary, DCAHistoryResponse, DCAListItem, DCAListResponse, DCAOptionKind, DCAOptions,
+    DCAQueryMsg, DCAResponse, DCAStatsResponse, DCAStrategy, DipTier, FailurePolicy, Frequency,
+    PostSwapAction,
+};
+use abstract_dca_app::state::{Config, DCAEntry, DCAStats, DCAStatus, DCATarget, ExecutedLeg};
 use abstract_dca_app::{
     contract::{DCA_APP_ID, DCA_APP_VERSION},
     msg::{AppInstantiateMsg, ConfigResponse, InstantiateMsg},
@@ -22,8 +26,9 @@ use croncat_integration_testing::DENOM;
 // Use prelude to get all the necessary imports
 use cw_orch::{anyhow, deploy::Deploy, prelude::*};
 
-use cosmwasm_std::{coin, Addr, Decimal, Uint128};
-use wyndex_bundle::{WynDex, EUR, USD};
+use cosmwasm_std::{coin, Addr, Decimal, Uint128, Validator};
+use cw_multi_test::StakingInfo;
+use wyndex_bundle::{WynDex, EUR, USD, WYND_TOKEN};
 
 // consts for testing
 const ADMIN: &str = "admin";
@@ -195,7 +200,8 @@ fn successful_install() -> anyhow::Result<()> {
                 dca_creation_amount: Uint128::new(5_000_000),
                 refill_threshold: Uint128::new(1_000_000),
                 max_spread: Decimal::percent(30),
-            }
+            },
+            halted: false,
         }
     );
     Ok(())
@@ -209,12 +215,14 @@ fn successful_swaps() -> anyhow::Result<()> {
     apps.dca_app.create_dca(
         WYNDEX_WITHOUT_CHAIN.to_owned(),
         Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
         OfferAsset::new(EUR, 100_u128),
         USD.into(),
     )?;
     apps.dca_app.create_dca(
         WYNDEX_WITHOUT_CHAIN.to_owned(),
         Frequency::EveryNBlocks(2),
+        DCAOptions::default(),
         OfferAsset::new(EUR, 250_u128),
         USD.into(),
     )?;
@@ -226,14 +234,33 @@ fn successful_swaps() -> anyhow::Result<()> {
         DCAResponse {
             dca: Some(DCAEntry {
                 source_asset: OfferAsset::new(EUR, 100_u128),
-                target_asset: USD.into(),
+                target: DCATarget::Asset(USD.into()),
                 frequency: Frequency::EveryNBlocks(1),
-                dex: WYNDEX_WITHOUT_CHAIN.to_owned()
+                dex: WYNDEX_WITHOUT_CHAIN.to_owned(),
+                total_budget: None,
+                max_executions: None,
+                status: DCAStatus::Active,
+                start: None,
+                end: None,
+                max_spread: None,
+                max_price: None,
+                min_price: None,
+                route: None,
+                strategy: DCAStrategy::Fixed,
+                dip_tiers: None,
+                target_holding: None,
+                recipient: None,
+                post_swap: None,
+                candidate_dexes: None,
+                fallback_dexes: None,
+                failure_policy: FailurePolicy::Stop,
+                gas_limit: None,
             }),
             pool_references: vec![PoolReference::new(
                 UniquePoolId::new(1),
                 PoolAddress::contract(apps.wyndex.eur_usd_pair.clone())
             )],
+            gas_limit: Some(300_000),
         }
     );
 
@@ -244,14 +271,33 @@ fn successful_swaps() -> anyhow::Result<()> {
         DCAResponse {
             dca: Some(DCAEntry {
                 source_asset: OfferAsset::new(EUR, 250_u128),
-                target_asset: USD.into(),
+                target: DCATarget::Asset(USD.into()),
                 frequency: Frequency::EveryNBlocks(2),
-                dex: WYNDEX_WITHOUT_CHAIN.to_owned()
+                dex: WYNDEX_WITHOUT_CHAIN.to_owned(),
+                total_budget: None,
+                max_executions: None,
+                status: DCAStatus::Active,
+                start: None,
+                end: None,
+                max_spread: None,
+                max_price: None,
+                min_price: None,
+                route: None,
+                strategy: DCAStrategy::Fixed,
+                dip_tiers: None,
+                target_holding: None,
+                recipient: None,
+                post_swap: None,
+                candidate_dexes: None,
+                fallback_dexes: None,
+                failure_policy: FailurePolicy::Stop,
+                gas_limit: None,
             }),
             pool_references: vec![PoolReference::new(
                 UniquePoolId::new(1),
                 PoolAddress::contract(apps.wyndex.eur_usd_pair)
             )],
+            gas_limit: Some(300_000),
         }
     );
 
@@ -272,6 +318,48 @@ fn successful_swaps() -> anyhow::Result<()> {
     let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
     assert_eq!(eur_balance, Uint128::new(9650));
 
+    // Executions are recorded
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert_eq!(history.executions.len(), 1);
+    let (execution_id, execution) = history.executions[0].clone();
+    assert_eq!(execution_id, 0);
+    assert_eq!(
+        execution.legs,
+        vec![ExecutedLeg {
+            offered: OfferAsset::new(EUR, 100_u128),
+            received: OfferAsset::new(USD, 98_u128),
+            price: Decimal::from_ratio(100_u128, 98_u128),
+        }]
+    );
+
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_2".to_owned(), None, None)?;
+    assert_eq!(history.executions.len(), 1);
+    assert_eq!(
+        history.executions[0].1.legs[0].received,
+        OfferAsset::new(USD, 237_u128)
+    );
+
+    // Running totals are kept
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Second swap gets less, the pool moved
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::new(426));
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(
+        stats.stats,
+        DCAStats {
+            executions: 2,
+            total_spent: Uint128::new(200),
+            total_received: Uint128::new(189),
+            average_price: Decimal::from_ratio(200_u128, 189_u128),
+            legs: vec![],
+            periods: 0,
+            last_price: Some(Decimal::from_ratio(100_u128, 91_u128)),
+            consecutive_failures: 0,
+        }
+    );
+
     Ok(())
 }
 
@@ -283,6 +371,7 @@ fn update_dca() -> anyhow::Result<()> {
     apps.dca_app.create_dca(
         WYNDEX_WITHOUT_CHAIN.to_owned(),
         Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
         OfferAsset::new(EUR, 150_u128),
         USD.into(),
     )?;
@@ -295,9 +384,12 @@ fn update_dca() -> anyhow::Result<()> {
         .task_hash;
 
     apps.dca_app.update_dca(
+        vec![],
         "dca_1".to_owned(),
+        None,
         Some(WYNDEX_WITHOUT_CHAIN.into()),
         Some(Frequency::EveryNBlocks(3)),
+        DCAOptions::default(),
         Some(OfferAsset::new(USD, 200_u128)),
         Some(EUR.into()),
     )?;
@@ -308,14 +400,33 @@ fn update_dca() -> anyhow::Result<()> {
         DCAResponse {
             dca: Some(DCAEntry {
                 source_asset: OfferAsset::new(USD, 200_u128),
-                target_asset: EUR.into(),
+                target: DCATarget::Asset(EUR.into()),
                 frequency: Frequency::EveryNBlocks(3),
-                dex: WYNDEX_WITHOUT_CHAIN.to_owned()
+                dex: WYNDEX_WITHOUT_CHAIN.to_owned(),
+                total_budget: None,
+                max_executions: None,
+                status: DCAStatus::Active,
+                start: None,
+                end: None,
+                max_spread: None,
+                max_price: None,
+                min_price: None,
+                route: None,
+                strategy: DCAStrategy::Fixed,
+                dip_tiers: None,
+                target_holding: None,
+                recipient: None,
+                post_swap: None,
+                candidate_dexes: None,
+                fallback_dexes: None,
+                failure_policy: FailurePolicy::Stop,
+                gas_limit: None,
             }),
             pool_references: vec![PoolReference::new(
                 UniquePoolId::new(1),
                 PoolAddress::contract(apps.wyndex.eur_usd_pair.clone())
             )],
+            gas_limit: Some(300_000),
         }
     );
 
@@ -330,9 +441,12 @@ fn update_dca() -> anyhow::Result<()> {
 
     // Now without updating frequency
     apps.dca_app.update_dca(
+        vec![],
         "dca_1".to_owned(),
         None,
         None,
+        None,
+        DCAOptions::default(),
         Some(OfferAsset::new(USD, 250_u128)),
         None,
     )?;
@@ -343,14 +457,33 @@ fn update_dca() -> anyhow::Result<()> {
         DCAResponse {
             dca: Some(DCAEntry {
                 source_asset: OfferAsset::new(USD, 250_u128),
-                target_asset: AssetEntry::new(EUR),
+                target: DCATarget::Asset(AssetEntry::new(EUR)),
                 frequency: Frequency::EveryNBlocks(3),
-                dex: WYNDEX_WITHOUT_CHAIN.to_owned()
+                dex: WYNDEX_WITHOUT_CHAIN.to_owned(),
+                total_budget: None,
+                max_executions: None,
+                status: DCAStatus::Active,
+                start: None,
+                end: None,
+                max_spread: None,
+                max_price: None,
+                min_price: None,
+                route: None,
+                strategy: DCAStrategy::Fixed,
+                dip_tiers: None,
+                target_holding: None,
+                recipient: None,
+                post_swap: None,
+                candidate_dexes: None,
+                fallback_dexes: None,
+                failure_policy: FailurePolicy::Stop,
+                gas_limit: None,
             }),
             pool_references: vec![PoolReference::new(
                 UniquePoolId::new(1),
                 PoolAddress::contract(apps.wyndex.eur_usd_pair)
             )],
+            gas_limit: Some(300_000),
         }
     );
 
@@ -363,30 +496,1746 @@ fn update_dca() -> anyhow::Result<()> {
 
     assert_eq!(task_hash_after_update, task_hash_after_second_update);
 
+    // Single asset dca can't become a basket
+    let res = apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        Some(vec![(EUR.into(), Decimal::one())]),
+        None,
+        None,
+        DCAOptions::default(),
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    // Options can be turned off again
+    apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        None,
+        None,
+        None,
+        DCAOptions {
+            max_price: Some(Decimal::percent(200)),
+            total_budget: Some(Uint128::new(1_000)),
+            ..Default::default()
+        },
+        None,
+        None,
+    )?;
+    apps.dca_app.update_dca(
+        vec![DCAOptionKind::MaxPrice, DCAOptionKind::TotalBudget],
+        "dca_1".to_owned(),
+        None,
+        None,
+        None,
+        DCAOptions::default(),
+        None,
+        None,
+    )?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?.dca.unwrap();
+    assert_eq!(dca.max_price, None);
+    assert_eq!(dca.total_budget, None);
+
     Ok(())
 }
 
 #[test]
 fn cancel_dca() -> anyhow::Result<()> {
-    let (_mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+    let (_mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
 
     // create dca
     apps.dca_app.create_dca(
         WYNDEX_WITHOUT_CHAIN.to_owned(),
         Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
         OfferAsset::new(EUR, 100_u128),
         USD.into(),
     )?;
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
 
+    let manager_addr = account.manager.address()?;
+    apps.dca_app.set_sender(&manager_addr);
     apps.dca_app.cancel_dca("dca_1".to_owned())?;
 
+    // Stats and history go with the dca
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.executions, 0);
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert!(history.executions.is_empty());
+
     let dca = apps.dca_app.dca("dca_1".to_owned())?;
     assert_eq!(
         dca,
         DCAResponse {
             dca: None,
-            pool_references: vec![]
+            pool_references: vec![],
+            gas_limit: None,
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn list_dcas() -> anyhow::Result<()> {
+    let (_mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+
+    // create 3 dcas
+    for amount in [100_u128, 200, 300] {
+        apps.dca_app.create_dca(
+            WYNDEX_WITHOUT_CHAIN.to_owned(),
+            Frequency::EveryNBlocks(1),
+            DCAOptions::default(),
+            OfferAsset::new(EUR, amount),
+            USD.into(),
+        )?;
+    }
+
+    let list: DCAListResponse = apps.dca_app.query(
+        &DCAQueryMsg::ListDCAs {
+            start_after: None,
+            limit: Some(2),
+            with_pool_references: Some(true),
+        }
+        .into(),
+    )?;
+    assert_eq!(
+        list.dcas,
+        vec![
+            DCAListItem {
+                dca_id: "dca_1".to_owned(),
+                dca: DCAEntry {
+                    source_asset: OfferAsset::new(EUR, 100_u128),
+                    target: DCATarget::Asset(USD.into()),
+                    frequency: Frequency::EveryNBlocks(1),
+                    dex: WYNDEX_WITHOUT_CHAIN.to_owned(),
+                    total_budget: None,
+                    max_executions: None,
+                    status: DCAStatus::Active,
+                    start: None,
+                    end: None,
+                    max_spread: None,
+                    max_price: None,
+                    min_price: None,
+                    route: None,
+                    strategy: DCAStrategy::Fixed,
+                    dip_tiers: None,
+                    target_holding: None,
+                    recipient: None,
+                    post_swap: None,
+                    candidate_dexes: None,
+                    fallback_dexes: None,
+                    failure_policy: FailurePolicy::Stop,
+                    gas_limit: None,
+                },
+                pool_references: Some(vec![PoolReference::new(
+                    UniquePoolId::new(1),
+                    PoolAddress::contract(apps.wyndex.eur_usd_pair.clone())
+                )]),
+            },
+            DCAListItem {
+                dca_id: "dca_2".to_owned(),
+                dca: DCAEntry {
+                    source_asset: OfferAsset::new(EUR, 200_u128),
+                    target: DCATarget::Asset(USD.into()),
+                    frequency: Frequency::EveryNBlocks(1),
+                    dex: WYNDEX_WITHOUT_CHAIN.to_owned(),
+                    total_budget: None,
+                    max_executions: None,
+                    status: DCAStatus::Active,
+                    start: None,
+                    end: None,
+                    max_spread: None,
+                    max_price: None,
+                    min_price: None,
+                    route: None,
+                    strategy: DCAStrategy::Fixed,
+                    dip_tiers: None,
+                    target_holding: None,
+                    recipient: None,
+                    post_swap: None,
+                    candidate_dexes: None,
+                    fallback_dexes: None,
+                    failure_policy: FailurePolicy::Stop,
+                    gas_limit: None,
+                },
+                pool_references: Some(vec![PoolReference::new(
+                    UniquePoolId::new(1),
+                    PoolAddress::contract(apps.wyndex.eur_usd_pair.clone())
+                )]),
+            }
+        ]
+    );
+
+    // Next page, without pool references
+    let list: DCAListResponse = apps.dca_app.query(
+        &DCAQueryMsg::ListDCAs {
+            start_after: Some("dca_2".to_owned()),
+            limit: None,
+            with_pool_references: None,
+        }
+        .into(),
+    )?;
+    assert_eq!(
+        list.dcas,
+        vec![DCAListItem {
+            dca_id: "dca_3".to_owned(),
+            dca: DCAEntry {
+                source_asset: OfferAsset::new(EUR, 300_u128),
+                target: DCATarget::Asset(USD.into()),
+                frequency: Frequency::EveryNBlocks(1),
+                dex: WYNDEX_WITHOUT_CHAIN.to_owned(),
+                total_budget: None,
+                max_executions: None,
+                status: DCAStatus::Active,
+                start: None,
+                end: None,
+                max_spread: None,
+                max_price: None,
+                min_price: None,
+                route: None,
+                strategy: DCAStrategy::Fixed,
+                dip_tiers: None,
+                target_holding: None,
+                recipient: None,
+                post_swap: None,
+                candidate_dexes: None,
+                fallback_dexes: None,
+                failure_policy: FailurePolicy::Stop,
+                gas_limit: None,
+            },
+            pool_references: None,
+        }]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn budget_completes_dca() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Budget or executions that allow no purchase are rejected
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            total_budget: Some(Uint128::zero()),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            max_executions: Some(0),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    // 250 budget with 100 per swap
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            total_budget: Some(Uint128::new(250)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Active);
+
+    // Last swap is shrunk to the remaining budget
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(9750));
+
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Completed);
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.executions, 3);
+    assert_eq!(stats.stats.total_spent, Uint128::new(250));
+
+    // Task is removed
+    let task_info = apps
+        .cron_cat_app
+        .task_info(apps.dca_app.addr_str()?, "dca_1".to_owned())?;
+    assert!(task_info.task.is_none());
+
+    let res = apps.dca_app.convert("dca_1".to_owned());
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn end_boundary_completes_dca() -> anyhow::Result<()> {
+    let (mock, _account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    let height = mock.block_info()?.height;
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            end: Some(DCABoundary::Height(height + 1)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Active);
+
+    // Last run before the end
+    mock.wait_blocks(1)?;
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Completed);
+
+    let task_info = apps
+        .cron_cat_app
+        .task_info(apps.dca_app.addr_str()?, "dca_1".to_owned())?;
+    assert!(task_info.task.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn skipped_last_run_completes_dca() -> anyhow::Result<()> {
+    let (mock, _account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    let height = mock.block_info()?.height;
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            end: Some(DCABoundary::Height(height + 1)),
+            max_price: Some(Decimal::percent(50)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Active);
+
+    // Last run is skipped by the price guard
+    mock.wait_blocks(1)?;
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Completed);
+
+    let task_info = apps
+        .cron_cat_app
+        .task_info(apps.dca_app.addr_str()?, "dca_1".to_owned())?;
+    assert!(task_info.task.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn ended_dca_reported_completed() -> anyhow::Result<()> {
+    let (mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+
+    let height = mock.block_info()?.height;
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            end: Some(DCABoundary::Height(height + 1)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    // No run handled the end
+    mock.wait_blocks(2)?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Completed);
+    let list: DCAListResponse = apps.dca_app.query(
+        &DCAQueryMsg::ListDCAs {
+            start_after: None,
+            limit: None,
+            with_pool_references: None,
         }
+        .into(),
+    )?;
+    assert_eq!(list.dcas[0].dca.status, DCAStatus::Completed);
+
+    // Can't be paused anymore
+    let res = apps.dca_app.pause_dca("dca_1".to_owned());
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn pause_and_resume_dca() -> anyhow::Result<()> {
+    let (_mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // create dca
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    let manager_addr = account.manager.address()?;
+    apps.dca_app.set_sender(&manager_addr);
+    apps.dca_app.pause_dca("dca_1".to_owned())?;
+
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Paused);
+    let task_info = apps
+        .cron_cat_app
+        .task_info(apps.dca_app.addr_str()?, "dca_1".to_owned())?;
+    assert!(task_info.task.is_none());
+
+    // Can't pause twice
+    let res = apps.dca_app.pause_dca("dca_1".to_owned());
+    assert!(res.is_err());
+
+    apps.dca_app.resume_dca("dca_1".to_owned())?;
+
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Active);
+    let task_info = apps
+        .cron_cat_app
+        .task_info(apps.dca_app.addr_str()?, "dca_1".to_owned())?;
+    assert!(task_info.task.is_some());
+
+    // Stats are kept
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.executions, 1);
+
+    Ok(())
+}
+
+#[test]
+fn halt_all() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // create 2 dcas
+    for _ in 0..2 {
+        apps.dca_app.create_dca(
+            WYNDEX_WITHOUT_CHAIN.to_owned(),
+            Frequency::EveryNBlocks(1),
+            DCAOptions::default(),
+            OfferAsset::new(EUR, 100_u128),
+            USD.into(),
+        )?;
+    }
+
+    // Only admin can halt
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    let res = apps.dca_app.halt_all(false);
+    assert!(res.is_err());
+
+    let manager_addr = account.manager.address()?;
+    apps.dca_app.set_sender(&manager_addr);
+    apps.dca_app.halt_all(false)?;
+    assert!(apps.dca_app.config()?.halted);
+
+    // Convert is skipped without failing the task
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(10_000));
+    let simulation = apps.dca_app.simulate_convert("dca_1".to_owned())?;
+    assert_eq!(simulation.skipped, Some("halted".to_owned()));
+    assert!(!simulation.refill);
+
+    // Dcas created or rescheduled during the halt get no task
+    apps.dca_app.set_sender(&manager_addr);
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+    apps.dca_app.update_dca(
+        vec![],
+        "dca_2".to_owned(),
+        None,
+        None,
+        Some(Frequency::EveryNBlocks(2)),
+        DCAOptions::default(),
+        None,
+        None,
+    )?;
+    for dca_id in ["dca_2", "dca_3"] {
+        let dca = apps.dca_app.dca(dca_id.to_owned())?;
+        assert_eq!(dca.dca.unwrap().status, DCAStatus::Paused);
+        let task_info = apps
+            .cron_cat_app
+            .task_info(apps.dca_app.addr_str()?, dca_id.to_owned())?;
+        assert!(task_info.task.is_none());
+    }
+
+    apps.dca_app.unhalt_all()?;
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Halt removing the tasks
+    apps.dca_app.set_sender(&manager_addr);
+    apps.dca_app.halt_all(true)?;
+    for dca_id in ["dca_1", "dca_2", "dca_3"] {
+        let dca = apps.dca_app.dca(dca_id.to_owned())?;
+        assert_eq!(dca.dca.unwrap().status, DCAStatus::Paused);
+        let task_info = apps
+            .cron_cat_app
+            .task_info(apps.dca_app.addr_str()?, dca_id.to_owned())?;
+        assert!(task_info.task.is_none());
+    }
+
+    // Can't resume while halted
+    let res = apps.dca_app.resume_dca("dca_1".to_owned());
+    assert!(res.is_err());
+
+    apps.dca_app.unhalt_all()?;
+    apps.dca_app.resume_dca("dca_1".to_owned())?;
+
+    Ok(())
+}
+
+#[test]
+fn max_spread_override() -> anyhow::Result<()> {
+    let (_mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+
+    // Max spread above 1 is rejected
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            max_spread: Some(Decimal::percent(150)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            max_spread: Some(Decimal::percent(5)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().max_spread, Some(Decimal::percent(5)));
+
+    Ok(())
+}
+
+#[test]
+fn price_guard_skips_swap() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Price of USD is a bit above 1 EUR
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            max_price: Some(Decimal::one()),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Nothing swapped
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(10_000));
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert!(history.executions.is_empty());
+
+    // Raise max price
+    let manager_addr = account.manager.address()?;
+    apps.dca_app.set_sender(&manager_addr);
+    apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        None,
+        None,
+        None,
+        DCAOptions {
+            max_price: Some(Decimal::percent(110)),
+            ..Default::default()
+        },
+        None,
+        None,
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(9_900));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_route() -> anyhow::Result<()> {
+    let (_mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+
+    // Route can't go through the target asset
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            route: Some(vec![USD.into()]),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn route_swaps_through_every_hop() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // USD is bought into WYND through EUR
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            route: Some(vec![EUR.into()]),
+            ..Default::default()
+        },
+        OfferAsset::new(USD, 100_u128),
+        WYND_TOKEN.into(),
+    )?;
+    mock.set_balance(
+        &account.proxy.address()?,
+        vec![coin(50_000_000, DENOM), coin(1_000, USD)],
+    )?;
+
+    // Every hop is swapped on its own pool
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    let pools = dca
+        .pool_references
+        .into_iter()
+        .map(|pool| pool.pool_address)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        pools,
+        vec![
+            PoolAddress::contract(apps.wyndex.eur_usd_pair.clone()),
+            PoolAddress::contract(apps.wyndex.wynd_eur_pair.clone()),
+        ]
+    );
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Nothing is left of the intermediate asset
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::new(900));
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::zero());
+    let wynd_balance = mock.query_balance(&account.proxy.address()?, WYND_TOKEN)?;
+    assert!(!wynd_balance.is_zero());
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert_eq!(history.executions[0].1.legs[0].received, wynd_balance);
+
+    // Route returning less than its min return is reverted
+    apps.dca_app.set_sender(&apps.dca_app.address()?);
+    let res = apps.dca_app.assert_min_return(
+        WYND_TOKEN.into(),
+        Uint128::zero(),
+        wynd_balance + Uint128::one(),
+    );
+    assert!(res.is_err());
+    apps.dca_app
+        .assert_min_return(WYND_TOKEN.into(), Uint128::zero(), wynd_balance)?;
+
+    Ok(())
+}
+
+#[test]
+fn basket_dca() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Weights have to add up to 1
+    let res = apps.dca_app.create_basket_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
+        OfferAsset::new(EUR, 100_u128),
+        vec![(USD.into(), Decimal::percent(50))],
+    );
+    assert!(res.is_err());
+
+    // Error names the unsupported option
+    let res = apps.dca_app.create_basket_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            max_price: Some(Decimal::one()),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        vec![(USD.into(), Decimal::one())],
+    );
+    assert!(format!("{:?}", res.unwrap_err()).contains("max_price is not supported"));
+
+    apps.dca_app.create_basket_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
+        OfferAsset::new(EUR, 100_u128),
+        vec![(USD.into(), Decimal::one())],
+    )?;
+
+    // Pool references of every leg
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(
+        dca.pool_references,
+        vec![PoolReference::new(
+            UniquePoolId::new(1),
+            PoolAddress::contract(apps.wyndex.eur_usd_pair.clone())
+        )]
+    );
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::new(98));
+
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.executions, 1);
+    assert_eq!(stats.stats.total_spent, Uint128::new(100));
+    assert_eq!(stats.stats.legs.len(), 1);
+    assert_eq!(stats.stats.legs[0].asset, AssetEntry::new(USD));
+    assert_eq!(stats.stats.legs[0].total_received, Uint128::new(98));
+
+    // Every leg of the run is recorded in one execution
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert_eq!(history.executions.len(), 1);
+    assert_eq!(
+        history.executions[0].1.legs,
+        vec![ExecutedLeg {
+            offered: OfferAsset::new(EUR, 100_u128),
+            received: OfferAsset::new(USD, 98_u128),
+            price: Decimal::from_ratio(100_u128, 98_u128),
+        }]
+    );
+
+    // Basket is updated as a whole, a single target asset is rejected
+    let manager_addr = account.manager.address()?;
+    apps.dca_app.set_sender(&manager_addr);
+    let res = apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        None,
+        None,
+        None,
+        DCAOptions::default(),
+        None,
+        Some(USD.into()),
+    );
+    assert!(res.is_err());
+    let res = apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        Some(vec![(USD.into(), Decimal::percent(50))]),
+        None,
+        None,
+        DCAOptions::default(),
+        None,
+        None,
+    );
+    assert!(res.is_err());
+    apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        Some(vec![(USD.into(), Decimal::one())]),
+        None,
+        None,
+        DCAOptions::default(),
+        Some(OfferAsset::new(EUR, 200_u128)),
+        None,
+    )?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(
+        dca.dca.unwrap().source_asset,
+        OfferAsset::new(EUR, 200_u128)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn value_averaging_strategy() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Target value that never grows is rejected
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            strategy: Some(DCAStrategy::ValueAveraging {
+                value_increment: Uint128::zero(),
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 150_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    // Target value grows by 100 EUR, at most 150 EUR is spent per period
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            strategy: Some(DCAStrategy::ValueAveraging {
+                value_increment: Uint128::new(100),
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 150_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+
+    // Nothing held yet, whole increment is bought
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(9_900));
+
+    // Holdings are worth less than 100 EUR after fees, the shortfall is bought
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert!(eur_balance < Uint128::new(9_800));
+    assert!(eur_balance > Uint128::new(9_750));
+
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.periods, 2);
+    assert_eq!(stats.stats.executions, 2);
+
+    Ok(())
+}
+
+#[test]
+fn balance_percentage_strategy() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Percentage can't be above 100%
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            strategy: Some(DCAStrategy::BalancePercentage {
+                percentage: Decimal::percent(200),
+                min_amount: None,
+                max_amount: None,
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            strategy: Some(DCAStrategy::BalancePercentage {
+                percentage: Decimal::percent(2),
+                min_amount: Some(Uint128::new(197)),
+                max_amount: None,
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+
+    // 2% of 10_000 EUR
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(9_800));
+
+    // 2% of 9_800 EUR is below the min amount
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(9_603));
+
+    Ok(())
+}
+
+#[test]
+fn exact_output_strategy() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            strategy: Some(DCAStrategy::ExactOutput {
+                amount: Uint128::new(98),
+                buffer: Decimal::percent(5),
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // At least the requested amount is received, the buffer is spent on top
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert!(usd_balance >= Uint128::new(98));
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert!(eur_balance < Uint128::new(9_900));
+    assert!(eur_balance > Uint128::new(9_880));
+
+    // Balance can't cover the required offer, the purchase is skipped
+    mock.set_balance(
+        &account.proxy.address()?,
+        vec![
+            coin(50_000_000, DENOM),
+            coin(50, EUR),
+            coin(usd_balance.u128(), USD),
+        ],
+    )?;
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(50));
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Active);
+
+    // Budget left can't cover the required offer, the dca is done
+    let manager_addr = account.manager.address()?;
+    apps.dca_app.set_sender(&manager_addr);
+    apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        None,
+        None,
+        None,
+        DCAOptions {
+            total_budget: Some(Uint128::new(200)),
+            ..Default::default()
+        },
+        None,
+        None,
+    )?;
+    mock.set_balance(
+        &account.proxy.address()?,
+        vec![coin(50_000_000, DENOM), coin(10_000, EUR)],
+    )?;
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(10_000));
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Completed);
+
+    Ok(())
+}
+
+#[test]
+fn dip_tiers_multiply_purchase() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Multiplier above the limit is rejected
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            dip_tiers: Some(vec![DipTier {
+                drop: Decimal::percent(10),
+                multiplier: Decimal::percent(1_100),
+            }]),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    // Buy twice as much after a 10% drop, three times after a 50% drop
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            dip_tiers: Some(vec![
+                DipTier {
+                    drop: Decimal::percent(10),
+                    multiplier: Decimal::percent(200),
+                },
+                DipTier {
+                    drop: Decimal::percent(50),
+                    multiplier: Decimal::percent(300),
+                },
+            ]),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+    // Sells USD, dropping its price
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
+        OfferAsset::new(USD, 2_000_u128),
+        EUR.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+
+    // No last price yet
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(9_900));
+
+    mock.set_balance(
+        &account.proxy.address()?,
+        vec![coin(50_000_000, DENOM), coin(9_900, EUR), coin(2_000, USD)],
+    )?;
+    apps.dca_app.convert("dca_2".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+
+    // Price dropped by more than 10%
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let new_eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance - new_eur_balance, Uint128::new(200));
+
+    // Multiplied purchase is capped by the balance
+    mock.set_balance(
+        &account.proxy.address()?,
+        vec![coin(50_000_000, DENOM), coin(2_000, USD)],
+    )?;
+    apps.dca_app.convert("dca_2".to_owned())?;
+    mock.set_balance(
+        &account.proxy.address()?,
+        vec![coin(50_000_000, DENOM), coin(150, EUR)],
+    )?;
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::zero());
+
+    Ok(())
+}
+
+#[test]
+fn target_holding_completes_dca() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            target_holding: Some(Uint128::new(150)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Active);
+
+    // Final purchase is sized by what is left to the goal
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert!(eur_balance > Uint128::new(9_800));
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert!(usd_balance >= Uint128::new(150));
+
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Completed);
+
+    Ok(())
+}
+
+#[test]
+fn exact_output_capped_by_target_holding() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            strategy: Some(DCAStrategy::ExactOutput {
+                amount: Uint128::new(98),
+                buffer: Decimal::percent(5),
+            }),
+            target_holding: Some(Uint128::new(150)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Last purchase only has to cover the shortfall, not the exact output amount
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert!(usd_balance >= Uint128::new(150));
+    assert!(usd_balance < Uint128::new(196));
+
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Completed);
+
+    Ok(())
+}
+
+#[test]
+fn recipient_receives_purchase() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+    let recipient = Addr::unchecked("recipient");
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            recipient: Some(recipient.to_string()),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Received amount is forwarded, nothing stays on the proxy
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::zero());
+    let usd_balance = mock.query_balance(&recipient, USD)?;
+    assert_eq!(usd_balance, Uint128::new(98));
+
+    Ok(())
+}
+
+#[test]
+fn delegation_conflicts_with_recipient() -> anyhow::Result<()> {
+    let (_mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+
+    // Purchase can't be both sent away and delegated
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            recipient: Some("recipient".to_owned()),
+            post_swap: Some(PostSwapAction::Delegate {
+                validator: "validator".to_owned(),
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    // Delegated purchase doesn't count towards the holdings of the proxy
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            target_holding: Some(Uint128::new(150)),
+            post_swap: Some(PostSwapAction::Delegate {
+                validator: "validator".to_owned(),
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            strategy: Some(DCAStrategy::ValueAveraging {
+                value_increment: Uint128::new(100),
+            }),
+            post_swap: Some(PostSwapAction::Delegate {
+                validator: "validator".to_owned(),
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn purchase_is_delegated() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Stake the purchased asset with a single validator
+    let block = mock.block_info()?;
+    mock.app
+        .borrow_mut()
+        .init_modules(|router, api, storage| -> anyhow::Result<()> {
+            router.staking.setup(
+                storage,
+                StakingInfo {
+                    bonded_denom: USD.to_owned(),
+                    unbonding_time: 60,
+                    apr: Decimal::percent(10),
+                },
+            )?;
+            router.staking.add_validator(
+                api,
+                storage,
+                &block,
+                Validator {
+                    address: "validator".to_owned(),
+                    commission: Decimal::percent(5),
+                    max_commission: Decimal::percent(10),
+                    max_change_rate: Decimal::percent(1),
+                },
+            )
+        })?;
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            post_swap: Some(PostSwapAction::Delegate {
+                validator: "validator".to_owned(),
+            }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Whole purchase is delegated, nothing is left on the proxy
+    let delegation = mock
+        .app
+        .borrow()
+        .wrap()
+        .query_delegation(account.proxy.address()?, "validator")?
+        .unwrap();
+    assert_eq!(delegation.amount, coin(98, USD));
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::zero());
+
+    Ok(())
+}
+
+#[test]
+fn failing_hook_does_not_revert() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Not a contract, so the hook always fails
+    apps.dca_app.add_hook("hook".to_owned())?;
+    let hooks = apps.dca_app.hooks()?;
+    assert_eq!(hooks.hooks, vec!["hook".to_owned()]);
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::new(98));
+
+    // Only admin can manage hooks
+    let res = apps.dca_app.remove_hook("hook".to_owned());
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn best_price_dex() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Unknown dex can't simulate and is left out
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            candidate_dexes: Some(vec!["unknown".to_owned()]),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::new(98));
+
+    // Chosen dex is recorded
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert_eq!(history.executions[0].1.dex, WYNDEX_WITHOUT_CHAIN.to_owned());
+
+    Ok(())
+}
+
+#[test]
+fn fallback_dex_rescues_failed_swap() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Unknown dex can't swap, the purchase is made on the fallback dex
+    apps.dca_app.create_dca(
+        "unknown".to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            fallback_dexes: Some(vec![WYNDEX_WITHOUT_CHAIN.to_owned()]),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    let res = apps.dca_app.convert("dca_1".to_owned())?;
+    assert!(res.events.iter().any(|event| event
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "fallback_dex" && attr.value == WYNDEX_WITHOUT_CHAIN)));
+
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::new(98));
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert_eq!(history.executions[0].1.dex, WYNDEX_WITHOUT_CHAIN.to_owned());
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.consecutive_failures, 0);
+
+    Ok(())
+}
+
+#[test]
+fn best_dex_picks_highest_quote() {
+    let dca = DCAEntry {
+        source_asset: OfferAsset::new(EUR, 100_u128),
+        target: DCATarget::Asset(USD.into()),
+        frequency: Frequency::EveryNBlocks(1),
+        dex: WYNDEX_WITHOUT_CHAIN.to_owned(),
+        total_budget: None,
+        max_executions: None,
+        status: DCAStatus::Active,
+        start: None,
+        end: None,
+        max_spread: None,
+        max_price: None,
+        min_price: None,
+        route: None,
+        strategy: DCAStrategy::Fixed,
+        dip_tiers: None,
+        target_holding: None,
+        recipient: None,
+        post_swap: None,
+        candidate_dexes: Some(vec!["astroport".to_owned(), "osmosis".to_owned()]),
+        fallback_dexes: None,
+        failure_policy: FailurePolicy::Stop,
+        gas_limit: None,
+    };
+
+    // Two priced dexes, the higher return wins
+    let best = dca.best_dex(vec![
+        (WYNDEX_WITHOUT_CHAIN.to_owned(), Uint128::new(98)),
+        ("astroport".to_owned(), Uint128::new(99)),
+        ("osmosis".to_owned(), Uint128::new(97)),
+    ]);
+    assert_eq!(best, "astroport".to_owned());
+
+    // Configured dex is kept on a tie
+    let best = dca.best_dex(vec![
+        (WYNDEX_WITHOUT_CHAIN.to_owned(), Uint128::new(98)),
+        ("astroport".to_owned(), Uint128::new(98)),
+    ]);
+    assert_eq!(best, WYNDEX_WITHOUT_CHAIN.to_owned());
+
+    // Without quotes the configured dex is used
+    assert_eq!(dca.best_dex(vec![]), WYNDEX_WITHOUT_CHAIN.to_owned());
+}
+
+#[test]
+fn fallback_dexes_exhausted() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Without any spread allowed the swap fails on every dex
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            max_spread: Some(Decimal::zero()),
+            fallback_dexes: Some(vec![WYNDEX_WITHOUT_CHAIN.to_owned()]),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 1_000_u128),
+        USD.into(),
+    )?;
+
+    // Stop fails the dca on the first failed execution
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Failed);
+
+    // Nothing was swapped or recorded
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(10_000));
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert!(history.executions.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn min_return_swap_is_internal() -> anyhow::Result<()> {
+    let (_mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+
+    // Only the app itself can swap with a min return or assert it
+    let res = apps.dca_app.swap_with_min_return(
+        USD.into(),
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Decimal::percent(30),
+        Uint128::new(90),
+        OfferAsset::new(EUR, 100_u128),
+    );
+    assert!(res.is_err());
+    let res = apps
+        .dca_app
+        .assert_min_return(USD.into(), Uint128::zero(), Uint128::zero());
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn failure_policy_fails_dca() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // Pausing before any failure is rejected
+    let res = apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            failure_policy: Some(FailurePolicy::PauseAfter { failures: 0 }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 1_000_u128),
+        USD.into(),
+    );
+    assert!(res.is_err());
+
+    // Without any spread allowed the swap always fails
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            max_spread: Some(Decimal::zero()),
+            failure_policy: Some(FailurePolicy::PauseAfter { failures: 2 }),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 1_000_u128),
+        USD.into(),
+    )?;
+
+    // Failed swap doesn't fail the execution
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(10_000));
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.consecutive_failures, 1);
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Active);
+
+    // Second failure in a row hits the limit
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.dca.unwrap().status, DCAStatus::Failed);
+
+    let res = apps.dca_app.convert("dca_1".to_owned());
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn task_gas_limit() -> anyhow::Result<()> {
+    let (_mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+
+    // Estimate is kept above the floor of a single swap
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            max_price: Some(Decimal::from_ratio(10_u128, 1_u128)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.gas_limit, Some(300_000));
+
+    // Every hook is called once per leg, the tasks are recreated with the new estimate
+    apps.dca_app.add_hook("hook".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.gas_limit, Some(340_000));
+    apps.dca_app.remove_hook("hook".to_owned())?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.gas_limit, Some(300_000));
+
+    // Override takes precedence over the estimate
+    apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        None,
+        None,
+        None,
+        DCAOptions {
+            gas_limit: Some(500_000),
+            ..Default::default()
+        },
+        None,
+        None,
+    )?;
+    let dca = apps.dca_app.dca("dca_1".to_owned())?;
+    assert_eq!(dca.gas_limit, Some(500_000));
+    assert_eq!(dca.dca.unwrap().gas_limit, Some(500_000));
+
+    // Zero gas limit is rejected
+    let res = apps.dca_app.update_dca(
+        vec![],
+        "dca_1".to_owned(),
+        None,
+        None,
+        None,
+        DCAOptions {
+            gas_limit: Some(0),
+            ..Default::default()
+        },
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    // Fallback dex may retry the swap
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            fallback_dexes: Some(vec![WYNDEX_WITHOUT_CHAIN.to_owned()]),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+    let dca = apps.dca_app.dca("dca_2".to_owned())?;
+    assert_eq!(dca.gas_limit, Some(420_000));
+
+    Ok(())
+}
+
+#[test]
+fn simulate_convert_previews_swap() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    // 250 budget with 100 per swap
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions {
+            total_budget: Some(Uint128::new(250)),
+            ..Default::default()
+        },
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    let simulation = apps.dca_app.simulate_convert("dca_1".to_owned())?;
+    assert!(!simulation.refill);
+    assert!(!simulation.completes);
+    assert_eq!(simulation.skipped, None);
+    assert_eq!(simulation.dex, Some(WYNDEX_WITHOUT_CHAIN.to_owned()));
+    assert_eq!(simulation.legs.len(), 1);
+    assert_eq!(
+        simulation.legs[0].offer_asset,
+        OfferAsset::new(EUR, 100_u128)
+    );
+    assert_eq!(simulation.legs[0].ask_asset, AssetEntry::new(USD));
+    // Single hop has one spread
+    assert_eq!(simulation.legs[0].spreads.len(), 1);
+    assert_eq!(simulation.legs[0].spreads[0].name, AssetEntry::new(USD));
+    assert!(simulation.capped_by.is_empty());
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Last swap is shrunk to the remaining budget
+    let simulation = apps.dca_app.simulate_convert("dca_1".to_owned())?;
+    assert_eq!(
+        simulation.legs[0].offer_asset,
+        OfferAsset::new(EUR, 50_u128)
+    );
+    assert_eq!(simulation.capped_by, vec!["budget".to_owned()]);
+
+    // Simulation doesn't change any state
+    let eur_balance = mock.query_balance(&account.proxy.address()?, EUR)?;
+    assert_eq!(eur_balance, Uint128::new(9800));
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.executions, 2);
+
+    // Swap returns what the simulation expected
+    apps.dca_app.convert("dca_1".to_owned())?;
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    let (_, execution) = history.executions.last().unwrap();
+    assert_eq!(
+        execution.legs[0].received.amount,
+        simulation.legs[0].return_amount
+    );
+
+    // Nothing to simulate for a completed dca
+    let res = apps.dca_app.simulate_convert("dca_1".to_owned());
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn dca_health() -> anyhow::Result<()> {
+    let (_mock, _account, _abstr, apps, _croncat_addrs) = setup()?;
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    let health = apps.dca_app.dca_health("dca_1".to_owned())?;
+    assert!(health.task_exists);
+    assert_eq!(health.task_balance, Uint128::new(5_000_000));
+    assert_eq!(health.refill_threshold, Uint128::new(1_000_000));
+    assert!(!health.execution_cost.is_zero());
+    assert_eq!(health.source_balance, Uint128::new(10_000));
+    assert_eq!(health.source_funded_executions, Some(100));
+    let gas_funded_executions = health.gas_funded_executions.unwrap();
+    assert!(gas_funded_executions > 0);
+    assert_eq!(
+        health.funded_executions,
+        Some(gas_funded_executions.min(100))
+    );
+    assert_eq!(health.failure, None);
+
+    // Paused dca fails when the task runs
+    apps.dca_app.pause_dca("dca_1".to_owned())?;
+    let health = apps.dca_app.dca_health("dca_1".to_owned())?;
+    assert_eq!(health.failure, Some("not_active".to_owned()));
+    // Runway is unknown without a task
+    assert_eq!(health.gas_funded_executions, None);
+    assert_eq!(health.funded_executions, None);
+
+    Ok(())
+}
+
+#[test]
+fn refill_in_purchased_asset() -> anyhow::Result<()> {
+    let (mock, account, _abstr, mut apps, croncat_addrs) = setup()?;
+
+    apps.dca_app.create_dca(
+        WYNDEX_WITHOUT_CHAIN.to_owned(),
+        Frequency::EveryNBlocks(1),
+        DCAOptions::default(),
+        OfferAsset::new(EUR, 100_u128),
+        USD.into(),
+    )?;
+
+    // Every run refills the task with the purchased asset
+    apps.dca_app.update_config(
+        Some(Uint128::new(10)),
+        None,
+        Some(USD.to_owned()),
+        Some(Uint128::MAX),
+    )?;
+    mock.set_balance(
+        &account.proxy.address()?,
+        vec![coin(50_000_000, DENOM), coin(10_000, EUR), coin(1_000, USD)],
+    )?;
+
+    apps.dca_app.set_sender(&croncat_addrs.manager);
+    apps.dca_app.convert("dca_1".to_owned())?;
+
+    // Refill is paid after the swap, so it isn't taken from the purchase
+    let usd_balance = mock.query_balance(&account.proxy.address()?, USD)?;
+    assert_eq!(usd_balance, Uint128::new(1_088));
+    let stats: DCAStatsResponse = apps.dca_app.dca_stats("dca_1".to_owned())?;
+    assert_eq!(stats.stats.total_received, Uint128::new(98));
+    let history: DCAHistoryResponse = apps.dca_app.dca_history("dca_1".to_owned(), None, None)?;
+    assert_eq!(
+        history.executions[0].1.legs[0].received,
+        OfferAsset::new(USD, 98_u128)
     );
 
     Ok(())